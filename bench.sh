@@ -25,7 +25,7 @@ git fetch --all
 commit_hash=$(git rev-list --max-count=1 --first-parent --before="$commit_date" main)
 commit_message=$(git log -n 1 --pretty=format:%s "$commit_hash")
 commit_message=${commit_message:0:60}
-commit_message=${commit_message//,/} #remove comma for csv compat
+commit_message="\"${commit_message//\"/\"\"}\"" #quote for csv compat
 
 echo "Checkout $commit_message ($commit_hash) for commit_date $commit_date"
 
@@ -34,21 +34,22 @@ git checkout "$commit_hash"
 #exec bench
 
 run_bench() {
-  benchoutput=$(cargo +nightly bench --features unstable | cargobench_to_csv)
+  # Fields are separated by the ASCII unit separator: unlike a tab it is not
+  # IFS whitespace, so `read` keeps empty fields such as a missing throughput
+  benchoutput=$(cargo +nightly bench --features unstable | cargobench_to_csv --delimiter $'\x1f')
 
   cd - || exit
   mkdir -p bench_results 2>/dev/null
 
   #store results
-  echo "$benchoutput"| while read -r line; do
-    IFS=',' read -ra bench_result <<< "$line"
-    bench_name=${bench_result[0]}
-    ns=${bench_result[1]}
-    variance=${bench_result[2]}
-    throughput=${bench_result[3]}
-
+  echo "$benchoutput"| while IFS=$'\x1f' read -r bench_name ns variance throughput _ unit target _; do
+    # Only times go into the history, not counters or benchmarks that did not run
+    [ "$unit" = ns ] || continue
+    # Benchmarks are kept apart by their bench target, names repeat across targets
+    out_file="bench_results/${target:+$target/}$bench_name"
+    mkdir -p "$(dirname "$out_file")"
     out="$ns,$variance,$throughput,$commit_hash,$commit_message,$commit_date,$rustc_version,$run_date_ts,$run_date"
-    echo "$out" >> "bench_results/$bench_name"
+    echo "$out" >> "$out_file"
 
   done
 
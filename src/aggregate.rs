@@ -0,0 +1,184 @@
+//! Summaries of benchmarks over repeated runs of the same suite.
+
+use std::collections::HashMap;
+
+use crate::{BenchOutcome, Benchmark, Measurement};
+
+/// Statistics of a benchmark's duration in ns over several runs.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Aggregate {
+    pub name: String,
+    pub shortname: String,
+    /// The bench target the benchmark belongs to, if known
+    pub target: Option<String>,
+    /// Number of runs the benchmark has a duration in
+    pub runs: usize,
+    pub min: f64,
+    pub median: f64,
+    pub mean: f64,
+    /// Sample standard deviation, 0 for a single run
+    pub stddev: f64,
+}
+
+impl Aggregate {
+    fn from_durations(bench: &Benchmark, durations: &mut [f64]) -> Aggregate {
+        durations.sort_by(f64::total_cmp);
+        let runs = durations.len();
+        let median = if runs % 2 == 0 {
+            (durations[runs / 2 - 1] + durations[runs / 2]) / 2.0
+        } else {
+            durations[runs / 2]
+        };
+        let mean = durations.iter().sum::<f64>() / runs as f64;
+        let stddev = if runs > 1 {
+            let squares: f64 = durations.iter().map(|ns| (ns - mean).powi(2)).sum();
+            (squares / (runs - 1) as f64).sqrt()
+        } else {
+            0.0
+        };
+        Aggregate {
+            name: bench.name.clone(),
+            shortname: bench.shortname.clone(),
+            target: bench.source.as_ref().map(|source| source.target.clone()),
+            runs,
+            min: durations[0],
+            median,
+            mean,
+            stddev,
+        }
+    }
+
+    /// A benchmark taking the median, with the standard deviation as variance
+    /// and `min`, `mean`, `stddev` and `runs` as additional measurements.
+    pub fn to_benchmark(&self) -> Benchmark {
+        let time = Measurement::new("time", self.median, "ns").with_dispersion(self.stddev);
+        let mut bench = Benchmark::from_time(self.name.clone(), time);
+        bench.measurements.extend([
+            Measurement::new("min", self.min, "ns"),
+            Measurement::new("mean", self.mean, "ns"),
+            Measurement::new("stddev", self.stddev, "ns"),
+            Measurement::new("runs", self.runs as f64, ""),
+        ]);
+        bench
+    }
+}
+
+/// Merges repeated runs into one [`Aggregate`] per bench target and benchmark
+/// name, in the order they first appear.
+///
+/// Only benchmarks that ran and measured a duration count. A name occurring
+/// several times in one run, e.g. in different bench binaries without cargo's
+/// `Running` lines, is kept apart by its occurrence: the n-th occurrence is
+/// merged with the n-th occurrences in the other runs.
+pub fn aggregate(runs: &[Vec<Benchmark>]) -> Vec<Aggregate> {
+    // Bench target, name and occurrence in the run
+    type Key<'a> = (Option<&'a str>, &'a str, usize);
+    let mut order: Vec<(Key, &Benchmark)> = Vec::new();
+    let mut durations: HashMap<Key, Vec<f64>> = HashMap::new();
+    for run in runs {
+        let mut occurrences: HashMap<(Option<&str>, &str), usize> = HashMap::new();
+        let timed = run.iter().filter(|bench| {
+            bench.outcome == BenchOutcome::Ok && bench.measurement("time").is_some()
+        });
+        for bench in timed {
+            let target = bench.source.as_ref().map(|source| source.target.as_str());
+            let occurrence = occurrences.entry((target, &bench.name)).or_insert(0);
+            let key = (target, bench.name.as_str(), *occurrence);
+            *occurrence += 1;
+            let entry = durations.entry(key).or_insert_with(|| {
+                order.push((key, bench));
+                Vec::new()
+            });
+            entry.push(bench.ns);
+        }
+    }
+    order
+        .into_iter()
+        .map(|(key, bench)| {
+            let durations = durations.get_mut(&key).unwrap();
+            Aggregate::from_durations(bench, durations)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::BufReader;
+
+    use super::*;
+    use crate::{parse_lines, BenchSource};
+
+    fn parse(text: &str) -> Vec<Benchmark> {
+        parse_lines(BufReader::new(text.as_bytes())).unwrap()
+    }
+
+    #[test]
+    fn aggregate_test() {
+        let runs = [
+            parse("test a ... bench: 100 ns/iter (+/- 1)\ntest b ... bench: 10 ns/iter (+/- 1)"),
+            parse("test a ... bench: 110 ns/iter (+/- 1)\ntest b ... ignored"),
+            parse("test a ... bench: 90 ns/iter (+/- 1)\ntest c ... bench: 5 ns/iter (+/- 1)"),
+            parse("test a ... bench: 120 ns/iter (+/- 1)"),
+        ];
+        let aggregates = aggregate(&runs);
+        let names: Vec<_> = aggregates.iter().map(|agg| agg.name.as_str()).collect();
+        assert_eq!(names, ["a", "b", "c"]);
+
+        let a = &aggregates[0];
+        assert_eq!(a.runs, 4);
+        assert_eq!(a.min, 90.0);
+        assert_eq!(a.median, 105.0);
+        assert_eq!(a.mean, 105.0);
+        assert_eq!(format!("{:.3}", a.stddev), "12.910");
+
+        let b = &aggregates[1];
+        assert_eq!((b.runs, b.median, b.stddev), (1, 10.0, 0.0));
+
+        let bench = a.to_benchmark();
+        assert_eq!(bench.ns, 105.0);
+        assert_eq!(bench.variance, a.stddev);
+        assert_eq!(bench.measurement("runs").unwrap().value, 4.0);
+    }
+
+    #[test]
+    fn aggregate_targets_test() {
+        let with_target = |mut benchmarks: Vec<Benchmark>, target: &str| {
+            for bench in &mut benchmarks {
+                bench.source = Some(BenchSource {
+                    src_path: None,
+                    target: target.to_string(),
+                    hash: None,
+                    crate_name: None,
+                });
+            }
+            benchmarks
+        };
+        let run = || {
+            let mut run = with_target(parse("test a ... bench: 100 ns/iter (+/- 1)"), "x");
+            run.extend(with_target(
+                parse("test a ... bench: 10 ns/iter (+/- 1)"),
+                "y",
+            ));
+            run
+        };
+        let aggregates = aggregate(&[run(), run()]);
+        assert_eq!(aggregates.len(), 2);
+        assert_eq!(aggregates[0].target.as_deref(), Some("x"));
+        assert_eq!(aggregates[1].target.as_deref(), Some("y"));
+        assert_eq!((aggregates[0].runs, aggregates[0].median), (2, 100.0));
+        assert_eq!((aggregates[1].runs, aggregates[1].median), (2, 10.0));
+
+        // Without targets, repeated names are kept apart by their occurrence
+        let run =
+            || parse("test a ... bench: 100 ns/iter (+/- 1)\ntest a ... bench: 10 ns/iter (+/- 1)");
+        let mut twice = run();
+        twice.extend(run());
+        let aggregates = aggregate(&[run(), twice]);
+        let stats: Vec<_> = aggregates
+            .iter()
+            .map(|agg| (agg.runs, agg.median))
+            .collect();
+        assert_eq!(stats, [(2, 100.0), (2, 10.0), (1, 100.0), (1, 10.0)]);
+    }
+}
@@ -0,0 +1,334 @@
+mod common;
+
+use std::{collections::HashMap, env, fs::File, io::BufReader, process};
+
+use common::{read_benchmarks, Arg, Args};
+use rust_bench_parser::{
+    compare_with, human::HumanDuration, parse_percent, BenchOutcome, Benchmark, Change,
+    MatchOptions, Thresholds, Verdict,
+};
+use serde::Deserialize;
+
+const USAGE: &str = "\
+Compares two cargo bench runs, matching benchmarks by name. Benchmarks left
+over are matched by their shortname if it is unique in both runs.
+
+Usage: cargobench_compare [OPTIONS] <OLD> <NEW>
+
+`-` reads stdin. Each change is classified as improved, regressed or within
+noise, or as not comparable if one run has no duration. With a threshold,
+exits with 1 if any benchmark got slower by more than its threshold, whatever
+its verdict, or failed or was ignored after running in the old run, and with
+2 on errors.
+
+Options:
+      --threshold <PERCENT>  Maximum allowed slowdown, e.g. 5%
+      --rename <OLD=NEW>     Matches OLD in the old run to NEW in the new run
+      --exact                Only matches by name and renames, not shortname
+      --config <PATH>        JSON config file with per benchmark thresholds
+                             and renames:
+                             {\"thresholds\": {\"default\": \"5%\",
+                              \"benchmarks\": {\"a::b\": \"10%\"}},
+                              \"renames\": {\"a::b\": \"a::c::b\"}}
+  -h, --help                 Print this help
+  -V, --version              Print the version
+";
+
+#[derive(Default)]
+struct Options {
+    old: String,
+    new: String,
+    threshold: Option<f64>,
+    config: Option<String>,
+    matching: MatchOptions,
+}
+
+/// The config file given with `--config`.
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Config {
+    #[serde(default)]
+    thresholds: Thresholds,
+    /// Old names to new names
+    #[serde(default)]
+    renames: HashMap<String, String>,
+}
+
+fn read_config(path: &str) -> Result<Config, String> {
+    let file = File::open(path).map_err(|err| format!("{}: {}", path, err))?;
+    serde_json::from_reader(BufReader::new(file)).map_err(|err| format!("{}: {}", path, err))
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options::default();
+    options.matching.shortnames = true;
+    let mut inputs = Vec::new();
+    let mut args = Args::new(args.into_iter());
+    while let Some(arg) = args.next_arg()? {
+        let flag = match arg {
+            Arg::Input(input) => {
+                inputs.push(input);
+                continue;
+            }
+            Arg::Flag(flag) => flag,
+        };
+        match flag.as_str() {
+            "--threshold" => options.threshold = Some(parse_percent(&args.value()?)?),
+            "--config" => options.config = Some(args.value()?),
+            "--rename" => options.matching.parse_rename(&args.value()?)?,
+            "--exact" => options.matching.shortnames = false,
+            "-h" | "--help" => {
+                print!("{}", USAGE);
+                process::exit(0);
+            }
+            "-V" | "--version" => {
+                println!("cargobench_compare {}", env!("CARGO_PKG_VERSION"));
+                process::exit(0);
+            }
+            _ => return Err(format!("unexpected argument '{}'", flag)),
+        }
+    }
+    match <[String; 2]>::try_from(inputs) {
+        Ok([old, new]) => {
+            options.old = old;
+            options.new = new;
+            Ok(options)
+        }
+        Err(_) => Err("expected two files, the old and the new run".to_string()),
+    }
+}
+
+fn signed_duration(ns: f64) -> String {
+    let sign = if ns > 0.0 { "+" } else { "" };
+    format!("{}{}", sign, HumanDuration::from_ns(ns))
+}
+
+fn row(change: &Change) -> [String; 7] {
+    let verdict = change.verdict();
+    if verdict == Verdict::NotComparable {
+        let duration = |bench: &Benchmark| match bench.measurement("time") {
+            Some(_) if bench.outcome == BenchOutcome::Ok => {
+                HumanDuration::from_ns(bench.ns).to_string()
+            }
+            _ => bench.outcome.to_string(),
+        };
+        return [
+            change.name().to_string(),
+            duration(&change.old),
+            duration(&change.new),
+            String::new(),
+            String::new(),
+            String::new(),
+            verdict.to_string(),
+        ];
+    }
+    [
+        change.name().to_string(),
+        HumanDuration::from_ns(change.old.ns).to_string(),
+        HumanDuration::from_ns(change.new.ns).to_string(),
+        signed_duration(change.diff_ns()),
+        change
+            .percent_change()
+            .map(|percent| format!("{:+.2}%", percent))
+            .unwrap_or_default(),
+        change
+            .speedup()
+            .map(|speedup| format!("x {:.2}", speedup))
+            .unwrap_or_default(),
+        verdict.to_string(),
+    ]
+}
+
+fn print_table(rows: &[[String; 7]]) {
+    let mut widths = [0; 7];
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    for row in rows {
+        // Names and verdicts are left aligned, numbers right aligned
+        let mut line = format!("{:<width$}", row[0], width = widths[0]);
+        for (cell, width) in row[1..6].iter().zip(&widths[1..6]) {
+            line.push_str(&format!("  {:>width$}", cell, width = width));
+        }
+        line.push_str(&format!("  {}", row[6]));
+        println!("{}", line.trim_end());
+    }
+}
+
+/// Returns whether any benchmark regressed beyond its threshold.
+fn run(options: &Options) -> Result<bool, String> {
+    let config = match &options.config {
+        Some(path) => read_config(path)?,
+        None => Config::default(),
+    };
+    let mut thresholds = config.thresholds;
+    if let Some(threshold) = options.threshold {
+        thresholds.default = Some(threshold);
+    }
+    // Renames on the command line take precedence over the config file
+    let mut matching = options.matching.clone();
+    for (old, new) in config.renames {
+        matching.renames.entry(old).or_insert(new);
+    }
+
+    let read = |path: &str| read_benchmarks(Some(path)).map_err(|err| format!("{}: {}", path, err));
+    let comparison = compare_with(&read(&options.old)?, &read(&options.new)?, &matching);
+
+    let mut rows =
+        vec![["name", "old", "new", "diff", "diff %", "speedup", "verdict"].map(String::from)];
+    rows.extend(comparison.changes.iter().map(row));
+    print_table(&rows);
+
+    for change in comparison
+        .changes
+        .iter()
+        .filter(|change| change.is_renamed())
+    {
+        println!("renamed: {} -> {}", change.old.name, change.new.name);
+    }
+    for change in &comparison.broken {
+        println!(
+            "not run: {} ({} in {}, {} in {})",
+            change.name(),
+            change.old.outcome,
+            options.old,
+            change.new.outcome,
+            options.new
+        );
+    }
+    for bench in &comparison.removed {
+        println!("only in {}: {}", options.old, bench.name);
+    }
+    for bench in &comparison.added {
+        println!("only in {}: {}", options.new, bench.name);
+    }
+
+    if thresholds == Thresholds::default() {
+        return Ok(false);
+    }
+    let regressions = comparison.regressions(&thresholds);
+    println!();
+    if regressions.is_empty() {
+        println!("no regressions beyond the threshold");
+        return Ok(false);
+    }
+    println!(
+        "{} of {} benchmarks regressed beyond the threshold:",
+        regressions.len(),
+        comparison.changes.len() + comparison.broken.len()
+    );
+    for (change, threshold) in regressions {
+        match change.percent_change() {
+            Some(percent) if change.new.outcome == BenchOutcome::Ok => println!(
+                "  {}  {:+.2}% (threshold {}%, {})",
+                change.name(),
+                percent,
+                threshold,
+                change.verdict()
+            ),
+            _ => println!("  {}  {}", change.name(), change.new.outcome),
+        }
+    }
+    Ok(true)
+}
+
+/// Runs with the arguments and returns the exit code, 1 if any benchmark
+/// regressed and 2 on errors.
+fn exit_code(args: impl IntoIterator<Item = String>) -> i32 {
+    let options = match parse_args(args) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("error: {}\n\n{}", err, USAGE);
+            return 2;
+        }
+    };
+    match run(&options) {
+        Ok(false) => 0,
+        Ok(true) => 1,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            2
+        }
+    }
+}
+
+fn main() {
+    process::exit(exit_code(env::args().skip(1)));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    /// A path in the temp dir, unique to this test binary and `name`.
+    fn temp_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("cargobench_compare-{}-{}", process::id(), name))
+    }
+
+    #[test]
+    fn parse_args_test() {
+        let options = parse_args(args(&[
+            "--threshold=5%",
+            "--rename",
+            "a=b",
+            "--exact",
+            "old.txt",
+            "-",
+        ]))
+        .unwrap();
+        assert_eq!(options.threshold, Some(5.0));
+        assert_eq!(options.matching.renames["a"], "b");
+        assert!(!options.matching.shortnames);
+        assert_eq!(
+            (options.old.as_str(), options.new.as_str()),
+            ("old.txt", "-")
+        );
+
+        let options = parse_args(args(&["--threshold", "2.5", "--", "--old", "new"])).unwrap();
+        assert_eq!(options.threshold, Some(2.5));
+        assert!(options.matching.shortnames);
+        assert_eq!(
+            (options.old.as_str(), options.new.as_str()),
+            ("--old", "new")
+        );
+
+        for invalid in [
+            &["old.txt"][..],
+            &["old.txt", "new.txt", "more.txt"],
+            &["--exact=1", "old.txt", "new.txt"],
+            &["old.txt", "new.txt", "--threshold"],
+            &["--threshold=five", "old.txt", "new.txt"],
+            &["--rename", "a", "old.txt", "new.txt"],
+            &["--unknown", "old.txt", "new.txt"],
+        ] {
+            assert!(parse_args(args(invalid)).is_err(), "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn exit_code_test() {
+        let old = temp_path("old.txt");
+        let new = temp_path("new.txt");
+        fs::write(&old, "test a ... bench: 100 ns/iter (+/- 1)\n").unwrap();
+        fs::write(&new, "test a ... bench: 200 ns/iter (+/- 1)\n").unwrap();
+        let (old_arg, new_arg) = (old.to_str().unwrap(), new.to_str().unwrap());
+
+        assert_eq!(exit_code(args(&[old_arg, new_arg])), 0);
+        assert_eq!(exit_code(args(&["--threshold=5%", old_arg, new_arg])), 1);
+        assert_eq!(exit_code(args(&["--threshold=5%", new_arg, old_arg])), 0);
+        assert_eq!(exit_code(args(&[old_arg])), 2);
+        let missing = temp_path("missing.txt");
+        assert_eq!(exit_code(args(&[old_arg, missing.to_str().unwrap()])), 2);
+
+        fs::remove_file(old).unwrap();
+        fs::remove_file(new).unwrap();
+    }
+}
@@ -1,21 +1,293 @@
-use std::io::{self, BufReader};
+mod common;
 
-use rust_bench_parser::parse_lines;
+use std::{
+    env,
+    fs::File,
+    io::{self, BufWriter, Write},
+    process,
+};
 
-fn main() -> io::Result<()> {
-    let benchmarks = parse_lines(BufReader::new(io::stdin()))?;
+use common::{read_benchmarks, Arg, Args};
+use regex::Regex;
+use rust_bench_parser::{
+    aggregate,
+    output::{Format, Row, RowWriter},
+    select::{Filter, Sort},
+    Benchmark,
+};
 
-    for benc in benchmarks {
-        println!(
-            "{},{},{},{}",
-            benc.name,
-            benc.ns,
-            benc.variance,
-            benc.throughput
-                .map(|throughput| throughput.to_string())
-                .unwrap_or("".to_string())
-        )
+const USAGE: &str = "\
+Converts cargo bench output into CSV and other formats.
+
+Usage: cargobench_to_csv [OPTIONS] [FILE]...
+
+Reads the files in order, or stdin if none are given. `-` reads stdin.
+Rows are tagged with the file they were read from when files are given.
+
+Options:
+  -o, --output <PATH>      Write to PATH instead of stdout
+      --format <FORMAT>    csv, tsv, json, ndjson or markdown [default: csv]
+      --header             Start CSV and TSV output with a header row
+      --delimiter <CHAR>   Delimiter of CSV and TSV output, `tab` for a tab,
+                           not a quote or line break
+      --filter <REGEX>     Keep benchmarks whose name or shortname matches
+      --exclude <REGEX>    Drop benchmarks whose name or shortname matches
+      --sort <KEY>[:DIR]   Sort by name, ns, variance or throughput, DIR is
+                           asc or desc [default: input order]
+      --aggregate          Treat each input as a repeated run of the same
+                           suite and write one row per benchmark with the
+                           runs, min, median, mean and stddev in ns
+  -h, --help               Print this help
+  -V, --version            Print the version
+";
+
+#[derive(Default)]
+struct Options {
+    inputs: Vec<String>,
+    output: Option<String>,
+    format: Format,
+    header: bool,
+    delimiter: Option<char>,
+    filter: Filter,
+    sort: Option<Sort>,
+    aggregate: bool,
+}
+
+fn parse_delimiter(value: &str) -> Result<char, String> {
+    if value == "tab" || value == "\\t" {
+        return Ok('\t');
+    }
+    let mut chars = value.chars();
+    match (chars.next(), chars.next()) {
+        // Quotes and line breaks can not be told apart from the field quoting
+        // and record separators of RFC 4180
+        (Some('"' | '\n' | '\r'), None) => Err(format!(
+            "invalid delimiter {:?}, quotes and line breaks are not allowed",
+            value
+        )),
+        (Some(c), None) => Ok(c),
+        _ => Err(format!("invalid delimiter '{}'", value)),
     }
+}
+
+fn parse_regex(value: &str) -> Result<Regex, String> {
+    Regex::new(value).map_err(|err| err.to_string())
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut args = Args::new(args.into_iter());
+    while let Some(arg) = args.next_arg()? {
+        let flag = match arg {
+            Arg::Input(input) => {
+                options.inputs.push(input);
+                continue;
+            }
+            Arg::Flag(flag) => flag,
+        };
+        match flag.as_str() {
+            "-o" | "--output" => options.output = Some(args.value()?),
+            "--format" => options.format = args.value()?.parse()?,
+            "--header" => options.header = true,
+            "--delimiter" => options.delimiter = Some(parse_delimiter(&args.value()?)?),
+            "--filter" => options.filter.include = Some(parse_regex(&args.value()?)?),
+            "--exclude" => options.filter.exclude = Some(parse_regex(&args.value()?)?),
+            "--sort" => options.sort = Some(args.value()?.parse()?),
+            "--aggregate" => options.aggregate = true,
+            "-h" | "--help" => {
+                print!("{}", USAGE);
+                process::exit(0);
+            }
+            "-V" | "--version" => {
+                println!("cargobench_to_csv {}", env!("CARGO_PKG_VERSION"));
+                process::exit(0);
+            }
+            _ => return Err(format!("unexpected argument '{}'", flag)),
+        }
+    }
+    Ok(options)
+}
 
-    Ok(())
+fn run(options: Options) -> Result<(), String> {
+    // The benchmarks of each input, with the file they were read from
+    let mut inputs: Vec<(Option<&str>, Vec<Benchmark>)> = Vec::new();
+    if options.inputs.is_empty() {
+        let read = read_benchmarks(None).map_err(|err| format!("stdin: {}", err))?;
+        inputs.push((None, read));
+    }
+    for path in &options.inputs {
+        let read = read_benchmarks(Some(path)).map_err(|err| format!("{}: {}", path, err))?;
+        inputs.push((Some(path.as_str()), read));
+    }
+    for (_, benchmarks) in &mut inputs {
+        benchmarks.retain(|bench| options.filter.matches(bench));
+    }
+    if options.aggregate {
+        let runs: Vec<Vec<Benchmark>> = inputs.into_iter().map(|(_, run)| run).collect();
+        return write_aggregates(&options, &runs);
+    }
+
+    let mut benchmarks: Vec<(Option<&str>, Benchmark)> = inputs
+        .into_iter()
+        .flat_map(|(path, run)| run.into_iter().map(move |bench| (path, bench)))
+        .collect();
+    if let Some(sort) = &options.sort {
+        benchmarks.sort_by(|(_, a), (_, b)| sort.compare(a, b));
+    }
+
+    let rows: Vec<Row> = benchmarks
+        .iter()
+        .flat_map(|(path, bench)| {
+            Row::from_benchmark(bench)
+                .into_iter()
+                .map(move |row| match path {
+                    Some(path) => row.with_file(path),
+                    None => row,
+                })
+        })
+        .collect();
+
+    open_output(&options)?
+        .write_rows(&rows)
+        .map_err(|err| err.to_string())
+}
+
+/// Aggregates the benchmarks of each input as one run.
+fn write_aggregates(options: &Options, runs: &[Vec<Benchmark>]) -> Result<(), String> {
+    let mut aggregates = aggregate(runs);
+    if let Some(sort) = &options.sort {
+        // Sorts by the median as `ns` and the stddev as `variance`
+        aggregates.sort_by(|a, b| sort.compare(&a.to_benchmark(), &b.to_benchmark()));
+    }
+    open_output(options)?
+        .write_aggregates(&aggregates)
+        .map_err(|err| err.to_string())
+}
+
+fn open_output(options: &Options) -> Result<RowWriter<Box<dyn Write>>, String> {
+    let writer: Box<dyn Write> = match &options.output {
+        Some(path) => Box::new(BufWriter::new(
+            File::create(path).map_err(|err| format!("{}: {}", path, err))?,
+        )),
+        None => Box::new(io::stdout().lock()),
+    };
+    let mut out = RowWriter::new(writer, options.format).header(options.header);
+    if let Some(delimiter) = options.delimiter {
+        out = out.delimiter(delimiter);
+    }
+    Ok(out)
+}
+
+/// Runs with the arguments and returns the exit code, 2 for invalid
+/// arguments and 1 for other errors.
+fn exit_code(args: impl IntoIterator<Item = String>) -> i32 {
+    let options = match parse_args(args) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("error: {}\n\n{}", err, USAGE);
+            return 2;
+        }
+    };
+    match run(options) {
+        Ok(()) => 0,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            1
+        }
+    }
+}
+
+fn main() {
+    process::exit(exit_code(env::args().skip(1)));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    /// A path in the temp dir, unique to this test binary and `name`.
+    fn temp_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("cargobench_to_csv-{}-{}", process::id(), name))
+    }
+
+    #[test]
+    fn parse_args_test() {
+        let options = parse_args(args(&[
+            "--format=tsv",
+            "-o",
+            "out.tsv",
+            "--delimiter=;",
+            "--sort",
+            "ns:desc",
+            "--header",
+            "a.txt",
+            "-",
+            "--",
+            "--aggregate",
+        ]))
+        .unwrap();
+        assert_eq!(options.format, Format::Tsv);
+        assert_eq!(options.output.as_deref(), Some("out.tsv"));
+        assert_eq!(options.delimiter, Some(';'));
+        assert!(options.sort.unwrap().descending);
+        assert!(options.header);
+        // `--aggregate` after `--` is a file
+        assert!(!options.aggregate);
+        assert_eq!(options.inputs, ["a.txt", "-", "--aggregate"]);
+
+        let options =
+            parse_args(args(&["--filter=a::", "--exclude", "slow", "--aggregate"])).unwrap();
+        assert!(options.filter.include.is_some() && options.filter.exclude.is_some());
+        assert!(options.aggregate);
+        assert!(options.inputs.is_empty());
+
+        for invalid in [
+            &["--format"][..],
+            &["--format=xml"],
+            &["--header=yes"],
+            &["--delimiter", "\""],
+            &["--filter", "("],
+            &["--unknown"],
+        ] {
+            assert!(parse_args(args(invalid)).is_err(), "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn exit_code_test() {
+        let input = temp_path("input.txt");
+        let output = temp_path("output.csv");
+        fs::write(&input, "test a ... bench: 100 ns/iter (+/- 1)\n").unwrap();
+        let input = input.to_str().unwrap();
+        let output_arg = output.to_str().unwrap();
+
+        assert_eq!(exit_code(args(&["--output", output_arg, input])), 0);
+        assert_eq!(
+            fs::read_to_string(&output).unwrap(),
+            format!("a,100,1,,ok,ns,{}\n", input)
+        );
+        assert_eq!(exit_code(args(&["--format"])), 2);
+        let missing = temp_path("missing.txt");
+        assert_eq!(exit_code(args(&[missing.to_str().unwrap()])), 1);
+
+        fs::remove_file(input).unwrap();
+        fs::remove_file(output).unwrap();
+    }
+
+    #[test]
+    fn parse_delimiter_test() {
+        assert_eq!(parse_delimiter(";"), Ok(';'));
+        assert_eq!(parse_delimiter("tab"), Ok('\t'));
+        assert_eq!(parse_delimiter("\\t"), Ok('\t'));
+        assert_eq!(parse_delimiter("\x1f"), Ok('\x1f'));
+        for invalid in ["\"", "\n", "\r", "", ",;"] {
+            assert!(parse_delimiter(invalid).is_err(), "{:?}", invalid);
+        }
+    }
 }
@@ -0,0 +1,133 @@
+//! Argument parsing and input reading shared by the binaries.
+
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader},
+};
+
+use rust_bench_parser::{parse_benchmarks, Benchmark};
+
+/// A command line argument.
+pub enum Arg {
+    /// A flag like `--format`, its value is read with [`Args::value`]
+    Flag(String),
+    /// A positional argument, `-` for stdin
+    Input(String),
+}
+
+/// Command line arguments, accepting both `--flag value` and `--flag=value`.
+/// Arguments after `--` are always inputs.
+pub struct Args<I> {
+    args: I,
+    flag: String,
+    inline_value: Option<String>,
+    only_inputs: bool,
+}
+
+impl<I: Iterator<Item = String>> Args<I> {
+    pub fn new(args: I) -> Self {
+        Args {
+            args,
+            flag: String::new(),
+            inline_value: None,
+            only_inputs: false,
+        }
+    }
+
+    pub fn next_arg(&mut self) -> Result<Option<Arg>, String> {
+        if let Some(value) = self.inline_value.take() {
+            return Err(format!("{} takes no value, got '{}'", self.flag, value));
+        }
+        loop {
+            let Some(arg) = self.args.next() else {
+                return Ok(None);
+            };
+            if self.only_inputs || arg == "-" || !arg.starts_with('-') {
+                return Ok(Some(Arg::Input(arg)));
+            }
+            if arg == "--" {
+                self.only_inputs = true;
+                continue;
+            }
+            self.flag = arg;
+            if self.flag.starts_with("--") {
+                if let Some((flag, value)) = self.flag.split_once('=') {
+                    self.inline_value = Some(value.to_string());
+                    self.flag = flag.to_string();
+                }
+            }
+            return Ok(Some(Arg::Flag(self.flag.clone())));
+        }
+    }
+
+    /// The value of the last flag.
+    pub fn value(&mut self) -> Result<String, String> {
+        self.inline_value
+            .take()
+            .or_else(|| self.args.next())
+            .ok_or_else(|| format!("{} needs a value", self.flag))
+    }
+}
+
+/// Parses a file, or stdin for `None` or `-`, printing its warnings.
+pub fn read_benchmarks(path: Option<&str>) -> io::Result<Vec<Benchmark>> {
+    let input: Box<dyn BufRead> = match path {
+        None | Some("-") => Box::new(BufReader::new(io::stdin())),
+        Some(path) => Box::new(BufReader::new(File::open(path)?)),
+    };
+    let (benchmarks, warnings) = parse_benchmarks(input)?;
+    for warning in warnings {
+        match path {
+            Some(path) => eprintln!("warning: {}: {}", path, warning),
+            None => eprintln!("warning: {}", warning),
+        }
+    }
+    Ok(benchmarks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The arguments as `--flag=value` or `input` strings.
+    fn parse(args: &[&str], flags_with_value: &[&str]) -> Result<Vec<String>, String> {
+        let mut args = Args::new(args.iter().map(|arg| arg.to_string()));
+        let mut parsed = Vec::new();
+        while let Some(arg) = args.next_arg()? {
+            parsed.push(match arg {
+                Arg::Flag(flag) if flags_with_value.contains(&flag.as_str()) => {
+                    format!("{}={}", flag, args.value()?)
+                }
+                Arg::Flag(flag) => flag,
+                Arg::Input(input) => input,
+            });
+        }
+        Ok(parsed)
+    }
+
+    #[test]
+    fn args_test() {
+        let parsed = parse(
+            &[
+                "--a", "1", "--a=2", "--a==3", "-o", "4", "--b", "-", "x", "--", "--b", "-o",
+            ],
+            &["--a", "-o"],
+        );
+        assert_eq!(
+            parsed.unwrap(),
+            ["--a=1", "--a=2", "--a==3", "-o=4", "--b", "-", "x", "--b", "-o"]
+        );
+        // Short flags take no inline value
+        assert_eq!(parse(&["-o=1"], &[]).unwrap(), ["-o=1"]);
+
+        assert_eq!(
+            parse(&["--b=1"], &["--a"]),
+            Err("--b takes no value, got '1'".to_string())
+        );
+        assert_eq!(
+            parse(&["--a"], &["--a"]),
+            Err("--a needs a value".to_string())
+        );
+        assert_eq!(parse(&[], &[]).unwrap(), Vec::<String>::new());
+    }
+}
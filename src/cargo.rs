@@ -0,0 +1,101 @@
+//! Parsing of the lines cargo prints around the bench binaries' output.
+
+use once_cell::sync::OnceCell;
+use regex::Regex;
+
+use crate::BenchSource;
+
+fn get_running_regex() -> &'static Regex {
+    static INSTANCE: OnceCell<Regex> = OnceCell::new();
+    INSTANCE.get_or_init(|| {
+        Regex::new(
+            r##"(?x)
+        ^\s*Running\s+                              # Running
+        (?:
+            (?:unittests\s+)?(?P<src_path>\S+)      # benches/foo.rs
+            \s+\((?P<binary>[^)]+)\)                # (target/release/deps/foo-abc123)
+        |
+            (?P<old_binary>\S+)                     # target/release/deps/foo-abc123
+        )\s*$
+    "##,
+        )
+        .unwrap()
+    })
+}
+
+/// Parses cargo's `Running` line, printed before the output of each binary.
+///
+/// ```text
+///      Running benches/foo.rs (target/release/deps/foo-3f5b1cbd0a8d1a2e)
+///      Running unittests src/lib.rs (target/release/deps/tantivy-3f5b1cbd0a8d1a2e)
+///      Running target/release/deps/foo-3f5b1cbd0a8d1a2e
+/// ```
+pub(crate) fn parse_running_line(line: &str) -> Option<BenchSource> {
+    let caps = get_running_regex().captures(line)?;
+    let binary = caps
+        .name("binary")
+        .or_else(|| caps.name("old_binary"))
+        .unwrap()
+        .as_str();
+    let file_name = binary.rsplit(['/', '\\']).next().unwrap();
+    let file_name = file_name.strip_suffix(".exe").unwrap_or(file_name);
+    let (target, hash) = match file_name.rsplit_once('-') {
+        Some((target, hash)) if hash.chars().all(|c| c.is_ascii_hexdigit()) => {
+            (target, Some(hash.to_string()))
+        }
+        _ => (file_name, None),
+    };
+    let src_path = caps.name("src_path").map(|m| m.as_str().to_string());
+    // Library and main binary targets are named after their crate
+    let crate_name = match src_path.as_deref() {
+        Some("src/lib.rs") | Some("src/main.rs") => Some(target.to_string()),
+        _ => None,
+    };
+    Some(BenchSource {
+        src_path,
+        target: target.to_string(),
+        hash,
+        crate_name,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_running_line_test() {
+        assert_eq!(
+            parse_running_line(
+                "     Running benches/foo.rs (target/release/deps/foo-3f5b1cbd0a8d1a2e)"
+            ),
+            Some(BenchSource {
+                src_path: Some("benches/foo.rs".to_string()),
+                target: "foo".to_string(),
+                hash: Some("3f5b1cbd0a8d1a2e".to_string()),
+                crate_name: None,
+            })
+        );
+        assert_eq!(
+            parse_running_line(
+                "     Running unittests src/lib.rs (target\\release\\deps\\fastfield_codecs-3f5b1cbd0a8d1a2e.exe)"
+            ),
+            Some(BenchSource {
+                src_path: Some("src/lib.rs".to_string()),
+                target: "fastfield_codecs".to_string(),
+                hash: Some("3f5b1cbd0a8d1a2e".to_string()),
+                crate_name: Some("fastfield_codecs".to_string()),
+            })
+        );
+        assert_eq!(
+            parse_running_line("     Running target/release/deps/foo-3f5b1cbd0a8d1a2e"),
+            Some(BenchSource {
+                src_path: None,
+                target: "foo".to_string(),
+                hash: Some("3f5b1cbd0a8d1a2e".to_string()),
+                crate_name: None,
+            })
+        );
+        assert_eq!(parse_running_line("running 3 tests"), None);
+    }
+}
@@ -0,0 +1,554 @@
+//! Comparison of two runs, matching benchmarks by name as cargo-benchcmp does.
+
+use std::{collections::HashMap, fmt};
+
+use serde::Deserialize;
+
+use crate::{stats, BenchOutcome, Benchmark, Interval};
+
+/// Samples needed on both sides to use a statistical test instead of ranges.
+const MIN_SAMPLES: usize = 8;
+
+/// p-value below which a difference of the samples is significant.
+const SIGNIFICANCE_LEVEL: f64 = 0.05;
+
+/// Whether a change stands out from the measurement noise.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    Improved,
+    Regressed,
+    WithinNoise,
+    /// One of the runs has no duration, e.g. the benchmark failed or was
+    /// ignored, or only reports counters
+    NotComparable,
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Verdict::Improved => write!(f, "improved"),
+            Verdict::Regressed => write!(f, "regressed"),
+            Verdict::WithinNoise => write!(f, "within noise"),
+            Verdict::NotComparable => write!(f, "not comparable"),
+        }
+    }
+}
+
+/// A benchmark present in both runs.
+#[derive(Clone, Debug)]
+pub struct Change {
+    pub old: Benchmark,
+    pub new: Benchmark,
+}
+
+impl Change {
+    pub fn name(&self) -> &str {
+        &self.new.name
+    }
+
+    /// Whether the benchmark was matched under a different name.
+    pub fn is_renamed(&self) -> bool {
+        self.old.name != self.new.name
+    }
+
+    /// Difference of the durations in ns, negative if the new run is faster.
+    pub fn diff_ns(&self) -> f64 {
+        self.new.ns - self.old.ns
+    }
+
+    /// Difference of the durations relative to the old duration in percent,
+    /// `None` if the old duration is 0.
+    pub fn percent_change(&self) -> Option<f64> {
+        if self.old.ns == 0.0 {
+            return None;
+        }
+        Some(self.diff_ns() / self.old.ns * 100.0)
+    }
+
+    /// Classifies the change, with the Mann-Whitney U test if both runs kept
+    /// enough samples, otherwise by whether the ranges of the runs overlap.
+    ///
+    /// The range is the confidence interval if reported (Criterion.rs),
+    /// otherwise `ns` +/- `variance / 2`, as libtest's `(+/- N)` is the whole
+    /// spread between the slowest and fastest iteration. Benchmarks without a measured duration in
+    /// either run are [`Verdict::NotComparable`].
+    pub fn verdict(&self) -> Verdict {
+        if !has_duration(&self.old) || !has_duration(&self.new) {
+            return Verdict::NotComparable;
+        }
+        let significant =
+            if self.old.samples.len() >= MIN_SAMPLES && self.new.samples.len() >= MIN_SAMPLES {
+                stats::mann_whitney_u(&self.old.samples, &self.new.samples) < SIGNIFICANCE_LEVEL
+            } else {
+                let (old, new) = (noise_range(&self.old), noise_range(&self.new));
+                old.upper < new.lower || new.upper < old.lower
+            };
+        match self.diff_ns() {
+            diff if significant && diff > 0.0 => Verdict::Regressed,
+            diff if significant && diff < 0.0 => Verdict::Improved,
+            _ => Verdict::WithinNoise,
+        }
+    }
+
+    /// How many times faster the new run is, `None` if the new duration is 0.
+    pub fn speedup(&self) -> Option<f64> {
+        if self.new.ns == 0.0 {
+            return None;
+        }
+        Some(self.old.ns / self.new.ns)
+    }
+}
+
+/// Whether the benchmark ran and measured a duration.
+fn has_duration(bench: &Benchmark) -> bool {
+    bench.outcome == BenchOutcome::Ok && bench.measurement("time").is_some()
+}
+
+/// The result of [`compare`].
+#[derive(Clone, Debug, Default)]
+pub struct Comparison {
+    /// Benchmarks in both runs, in the order of the new run
+    pub changes: Vec<Change>,
+    /// Benchmarks in both runs that failed or were ignored in at least one of
+    /// them, in the order of the new run
+    pub broken: Vec<Change>,
+    /// Benchmarks only in the old run
+    pub removed: Vec<Benchmark>,
+    /// Benchmarks only in the new run
+    pub added: Vec<Benchmark>,
+}
+
+impl Comparison {
+    /// The changes slower than their threshold, with the threshold crossed.
+    /// The [`Verdict`] does not matter, a slowdown beyond the threshold is a
+    /// regression even if the runs were too noisy to tell it apart.
+    ///
+    /// Benchmarks with a threshold that ran in the old run but failed or were
+    /// ignored in the new one count as regressions too, after the changes.
+    pub fn regressions<'a>(&'a self, thresholds: &Thresholds) -> Vec<(&'a Change, f64)> {
+        let slower = self.changes.iter().filter_map(|change| {
+            let threshold = thresholds.get(change.name())?;
+            let percent = change.percent_change()?;
+            (percent > threshold).then_some((change, threshold))
+        });
+        let broken = self.broken.iter().filter_map(|change| {
+            let threshold = thresholds.get(change.name())?;
+            let broke = change.old.outcome == BenchOutcome::Ok;
+            broke.then_some((change, threshold))
+        });
+        slower.chain(broken).collect()
+    }
+}
+
+/// Maximum allowed slowdowns in percent, by benchmark name.
+///
+/// Deserializes from `{"default": "5%", "benchmarks": {"a::b": "10%"}}`.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Thresholds {
+    /// Threshold for benchmarks without an override, none are checked if unset
+    #[serde(default, deserialize_with = "deserialize_percent")]
+    pub default: Option<f64>,
+    /// Per benchmark overrides of the default
+    #[serde(default, deserialize_with = "deserialize_percents")]
+    pub benchmarks: HashMap<String, f64>,
+}
+
+impl Thresholds {
+    /// The threshold of a benchmark, `None` if it is not checked.
+    pub fn get(&self, name: &str) -> Option<f64> {
+        self.benchmarks.get(name).copied().or(self.default)
+    }
+}
+
+/// Parses a non-negative percentage like `5%` or `2.5`.
+pub fn parse_percent(text: &str) -> Result<f64, String> {
+    let number = text.trim().trim_end_matches('%').trim_end();
+    match number.parse::<f64>() {
+        Ok(percent) if percent >= 0.0 && percent.is_finite() => Ok(percent),
+        _ => Err(format!("invalid percentage '{}', expected e.g. 5%", text)),
+    }
+}
+
+/// A percentage in a config file, either a number or a string like `"5%"`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PercentJson {
+    Number(f64),
+    Text(String),
+}
+
+impl PercentJson {
+    fn parse(self) -> Result<f64, String> {
+        match self {
+            PercentJson::Number(percent) => parse_percent(&percent.to_string()),
+            PercentJson::Text(text) => parse_percent(&text),
+        }
+    }
+}
+
+fn deserialize_percent<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<f64>, D::Error> {
+    let percent = Option::<PercentJson>::deserialize(deserializer)?;
+    percent
+        .map(PercentJson::parse)
+        .transpose()
+        .map_err(serde::de::Error::custom)
+}
+
+fn deserialize_percents<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<HashMap<String, f64>, D::Error> {
+    HashMap::<String, PercentJson>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(name, percent)| Ok((name, percent.parse()?)))
+        .collect::<Result<_, String>>()
+        .map_err(serde::de::Error::custom)
+}
+
+fn noise_range(bench: &Benchmark) -> Interval {
+    bench.interval().unwrap_or(Interval {
+        lower: bench.ns - bench.variance / 2.0,
+        estimate: bench.ns,
+        upper: bench.ns + bench.variance / 2.0,
+    })
+}
+
+/// How [`compare_with`] matches benchmarks of the old run to the new run.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MatchOptions {
+    /// New names of benchmarks of the old run, e.g. after moving a module
+    pub renames: HashMap<String, String>,
+    /// Matches the benchmarks left over by name if their shortname is unique
+    /// among the left over benchmarks of both runs
+    pub shortnames: bool,
+}
+
+impl MatchOptions {
+    /// Adds a rename from `old=new`.
+    pub fn parse_rename(&mut self, rename: &str) -> Result<(), String> {
+        match rename.split_once('=') {
+            Some((old, new)) if !old.is_empty() && !new.is_empty() => {
+                self.renames.insert(old.to_string(), new.to_string());
+                Ok(())
+            }
+            _ => Err(format!("invalid rename '{}', expected old=new", rename)),
+        }
+    }
+}
+
+/// Matches the benchmarks of two runs by name. If a name occurs several
+/// times, the occurrences are matched in order.
+///
+/// If cargo printed the bench target of every benchmark in both runs, the
+/// benchmarks are matched by bench target and name, so that identically named
+/// benchmarks of different targets are kept apart.
+pub fn compare(old: &[Benchmark], new: &[Benchmark]) -> Comparison {
+    compare_with(old, new, &MatchOptions::default())
+}
+
+/// Like [`compare`], applying the renames first and falling back to
+/// shortnames if enabled.
+pub fn compare_with(old: &[Benchmark], new: &[Benchmark], options: &MatchOptions) -> Comparison {
+    let with_targets = old.iter().chain(new).all(|bench| bench.source.is_some());
+    let target = |bench: &Benchmark| -> Option<String> {
+        let source = bench.source.as_ref().filter(|_| with_targets)?;
+        Some(source.target.clone())
+    };
+    let renamed = |bench: &Benchmark| -> String {
+        options
+            .renames
+            .get(&bench.name)
+            .unwrap_or(&bench.name)
+            .to_string()
+    };
+    let mut unmatched: HashMap<(Option<String>, String), Vec<usize>> = HashMap::new();
+    for (index, bench) in old.iter().enumerate().rev() {
+        let key = (target(bench), renamed(bench));
+        unmatched.entry(key).or_default().push(index);
+    }
+
+    // Index of the old benchmark each new benchmark is matched to
+    let mut matches: Vec<Option<usize>> = new
+        .iter()
+        .map(|bench| {
+            let key = (target(bench), bench.name.clone());
+            unmatched.get_mut(&key).and_then(Vec::pop)
+        })
+        .collect();
+    let mut matched = vec![false; old.len()];
+    for index in matches.iter().flatten() {
+        matched[*index] = true;
+    }
+
+    if options.shortnames {
+        let mut left_old: HashMap<&str, Vec<usize>> = HashMap::new();
+        for (index, bench) in old.iter().enumerate() {
+            if !matched[index] {
+                left_old.entry(&bench.shortname).or_default().push(index);
+            }
+        }
+        let mut left_new: HashMap<&str, Vec<usize>> = HashMap::new();
+        for (index, bench) in new.iter().enumerate() {
+            if matches[index].is_none() {
+                left_new.entry(&bench.shortname).or_default().push(index);
+            }
+        }
+        for (shortname, new_indices) in left_new {
+            if let (Some([old_index]), [new_index]) = (
+                left_old.get(shortname).map(Vec::as_slice),
+                new_indices.as_slice(),
+            ) {
+                matches[*new_index] = Some(*old_index);
+                matched[*old_index] = true;
+            }
+        }
+    }
+
+    let mut comparison = Comparison::default();
+    for (bench, index) in new.iter().zip(matches) {
+        let Some(index) = index else {
+            comparison.added.push(bench.clone());
+            continue;
+        };
+        let change = Change {
+            old: old[index].clone(),
+            new: bench.clone(),
+        };
+        if change.old.outcome == BenchOutcome::Ok && change.new.outcome == BenchOutcome::Ok {
+            comparison.changes.push(change);
+        } else {
+            comparison.broken.push(change);
+        }
+    }
+    comparison.removed = old
+        .iter()
+        .zip(matched)
+        .filter(|(_, matched)| !matched)
+        .map(|(bench, _)| bench.clone())
+        .collect();
+    comparison
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::BufReader;
+
+    use super::*;
+    use crate::parse_lines;
+
+    fn parse(text: &str) -> Vec<Benchmark> {
+        parse_lines(BufReader::new(text.as_bytes())).unwrap()
+    }
+
+    #[test]
+    fn compare_test() {
+        let old = parse(
+            "
+test a::same ... bench:       1,000 ns/iter (+/- 10)
+test a::gone ... bench:         500 ns/iter (+/- 10)
+test a::fast ... bench:       2,000 ns/iter (+/- 10)
+",
+        );
+        let new = parse(
+            "
+test a::fast ... bench:       1,000 ns/iter (+/- 10)
+test a::same ... bench:       1,100 ns/iter (+/- 10)
+test a::new  ... bench:         100 ns/iter (+/- 10)
+",
+        );
+        let comparison = compare(&old, &new);
+
+        let names: Vec<_> = comparison.changes.iter().map(Change::name).collect();
+        assert_eq!(names, ["a::fast", "a::same"]);
+        assert_eq!(comparison.removed[0].name, "a::gone");
+        assert_eq!(comparison.added[0].name, "a::new");
+
+        let fast = &comparison.changes[0];
+        assert_eq!(fast.diff_ns(), -1000.0);
+        assert_eq!(fast.percent_change(), Some(-50.0));
+        assert_eq!(fast.speedup(), Some(2.0));
+        let same = &comparison.changes[1];
+        assert_eq!(same.diff_ns(), 100.0);
+        assert_eq!(same.percent_change().map(f64::round), Some(10.0));
+    }
+
+    #[test]
+    fn compare_targets_test() {
+        let old = parse(
+            "
+     Running benches/a.rs (target/release/deps/a-3f5b1cbd0a8d1a2e)
+test bench_x ... bench:         100 ns/iter (+/- 1)
+     Running benches/b.rs (target/release/deps/b-3f5b1cbd0a8d1a2e)
+test bench_x ... bench:       5,000 ns/iter (+/- 1)
+",
+        );
+        let new = parse(
+            "
+     Running benches/b.rs (target/release/deps/b-0123456789abcdef)
+test bench_x ... bench:       5,000 ns/iter (+/- 1)
+",
+        );
+        let comparison = compare(&old, &new);
+        assert_eq!(comparison.changes.len(), 1);
+        assert_eq!(comparison.changes[0].old.ns, 5000.0);
+        assert_eq!(comparison.removed[0].ns, 100.0);
+
+        // Without targets on one side, benchmarks are matched by name
+        let new = parse("test bench_x ... bench: 5,000 ns/iter (+/- 1)");
+        let comparison = compare(&old, &new);
+        assert_eq!(comparison.changes[0].old.ns, 100.0);
+    }
+
+    #[test]
+    fn compare_duplicate_names_test() {
+        let old = parse("test a ... bench: 1 ns/iter (+/- 0)\ntest a ... bench: 2 ns/iter (+/- 0)");
+        let new = parse("test a ... bench: 3 ns/iter (+/- 0)");
+        let comparison = compare(&old, &new);
+        assert_eq!(comparison.changes[0].old.ns, 1.0);
+        assert_eq!(comparison.removed[0].ns, 2.0);
+    }
+
+    #[test]
+    fn regressions_test() {
+        let old =
+            parse("test a ... bench: 100 ns/iter (+/- 0)\ntest b ... bench: 100 ns/iter (+/- 0)");
+        let new =
+            parse("test a ... bench: 106 ns/iter (+/- 0)\ntest b ... bench: 106 ns/iter (+/- 0)");
+        let comparison = compare(&old, &new);
+
+        let thresholds: Thresholds =
+            serde_json::from_str(r#"{"default": "5%", "benchmarks": {"b": 10}}"#).unwrap();
+        assert_eq!(thresholds.get("a"), Some(5.0));
+        assert_eq!(thresholds.get("b"), Some(10.0));
+        let regressions = comparison.regressions(&thresholds);
+        assert_eq!(regressions.len(), 1);
+        assert_eq!(regressions[0].0.name(), "a");
+        assert_eq!(regressions[0].1, 5.0);
+
+        assert!(comparison.regressions(&Thresholds::default()).is_empty());
+
+        // Benchmarks that ran before and now fail or are ignored
+        let old = parse(
+            "test a ... bench: 100 ns/iter (+/- 0)\ntest b ... bench: 100 ns/iter (+/- 0)\ntest c ... ignored",
+        );
+        let new = parse("test a ... ignored\ntest b ... FAILED\ntest c ... ignored");
+        let comparison = compare(&old, &new);
+        assert!(comparison.changes.is_empty());
+        assert_eq!(comparison.broken.len(), 3);
+        let regressions = comparison.regressions(&thresholds);
+        let names: Vec<_> = regressions
+            .iter()
+            .map(|(change, _)| change.name())
+            .collect();
+        assert_eq!(names, ["a", "b"]);
+        assert!(serde_json::from_str::<Thresholds>(r#"{"default": "-5%"}"#).is_err());
+        assert!(serde_json::from_str::<Thresholds>(r#"{"threshold": "5%"}"#).is_err());
+
+        assert_eq!(parse_percent("2.5 %"), Ok(2.5));
+        assert_eq!(parse_percent("5"), Ok(5.0));
+        assert!(parse_percent("five").is_err());
+    }
+
+    #[test]
+    fn verdict_test() {
+        let old = parse(
+            "
+test a::faster ... bench:       1,000 ns/iter (+/- 10)
+test a::jitter ... bench:       1,000 ns/iter (+/- 30)
+test a::slower ... bench:       1,000 ns/iter (+/- 10)
+",
+        );
+        let new = parse(
+            "
+test a::faster ... bench:         900 ns/iter (+/- 10)
+test a::jitter ... bench:       1,020 ns/iter (+/- 30)
+test a::slower ... bench:       1,100 ns/iter (+/- 10)
+",
+        );
+        let comparison = compare(&old, &new);
+        let verdicts: Vec<_> = comparison.changes.iter().map(Change::verdict).collect();
+        assert_eq!(
+            verdicts,
+            [Verdict::Improved, Verdict::WithinNoise, Verdict::Regressed]
+        );
+        let thresholds = Thresholds {
+            default: Some(1.0),
+            ..Thresholds::default()
+        };
+        // Slower than the threshold fails the gate even within noise
+        let regressions = comparison.regressions(&thresholds);
+        let names: Vec<_> = regressions
+            .iter()
+            .map(|(change, _)| change.name())
+            .collect();
+        assert_eq!(names, ["a::jitter", "a::slower"]);
+
+        // libtest's spread covers both sides of `ns`, the ranges do not overlap
+        let old = parse("test a ... bench: 1,000 ns/iter (+/- 300)");
+        let new = parse("test a ... bench: 1,500 ns/iter (+/- 300)");
+        assert_eq!(compare(&old, &new).changes[0].verdict(), Verdict::Regressed);
+
+        // Overlapping ranges, but samples that clearly differ
+        let mut change = comparison.changes[1].clone();
+        change.old.samples = (0..20).map(|i| 1000.0 + (i % 5) as f64).collect();
+        change.new.samples = (0..20).map(|i| 1010.0 + (i % 5) as f64).collect();
+        assert_eq!(change.verdict(), Verdict::Regressed);
+        change.new.samples = change.old.samples.clone();
+        assert_eq!(change.verdict(), Verdict::WithinNoise);
+
+        // Failed, ignored and counter only benchmarks have no duration
+        let old =
+            parse("test a ... bench: 100 ns/iter (+/- 1)\ntest b ... bench: 100 ns/iter (+/- 1)");
+        let new = parse("test a ... FAILED\ntest b ... ignored");
+        let comparison = compare(&old, &new);
+        let verdicts: Vec<_> = comparison.broken.iter().map(Change::verdict).collect();
+        assert_eq!(verdicts, [Verdict::NotComparable, Verdict::NotComparable]);
+        let mut change = comparison.broken[0].clone();
+        change.new = change.old.clone();
+        change
+            .new
+            .measurements
+            .retain(|measurement| measurement.metric != "time");
+        assert_eq!(change.verdict(), Verdict::NotComparable);
+    }
+
+    #[test]
+    fn compare_renames_test() {
+        let old = parse(
+            "
+test fastfield::bench::foo ... bench:       1,000 ns/iter (+/- 10)
+test fastfield::bench::bar ... bench:       1,000 ns/iter (+/- 10)
+test a::dup                ... bench:       1,000 ns/iter (+/- 10)
+test b::dup                ... bench:       1,000 ns/iter (+/- 10)
+",
+        );
+        let new = parse(
+            "
+test fastfield::codecs::bench::foo ... bench:       1,000 ns/iter (+/- 10)
+test moved::bar                    ... bench:       1,000 ns/iter (+/- 10)
+test c::dup                        ... bench:       1,000 ns/iter (+/- 10)
+",
+        );
+        assert_eq!(compare(&old, &new).changes.len(), 0);
+
+        let mut options = MatchOptions::default();
+        options
+            .parse_rename("fastfield::bench::foo=fastfield::codecs::bench::foo")
+            .unwrap();
+        assert!(options.parse_rename("foo").is_err());
+        let comparison = compare_with(&old, &new, &options);
+        assert_eq!(comparison.changes.len(), 1);
+        assert!(comparison.changes[0].is_renamed());
+        assert_eq!(comparison.changes[0].old.name, "fastfield::bench::foo");
+
+        options.shortnames = true;
+        let comparison = compare_with(&old, &new, &options);
+        let names: Vec<_> = comparison.changes.iter().map(Change::name).collect();
+        assert_eq!(names, ["fastfield::codecs::bench::foo", "moved::bar"]);
+        // `dup` is ambiguous in the old run
+        assert_eq!(comparison.added[0].name, "c::dup");
+        assert_eq!(comparison.removed.len(), 2);
+    }
+}
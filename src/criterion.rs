@@ -0,0 +1,422 @@
+//! Parsing of Criterion.rs console output and `target/criterion` directories.
+//!
+//! ```text
+//! fib 20                  time:   [26.029 µs 26.251 µs 26.505 µs]
+//!                         thrpt:  [36.116 MiB/s 36.466 MiB/s 36.776 MiB/s]
+//!                         change: [-1.2% +0.3% +1.8%] (p = 0.71 > 0.05)
+//! ```
+//!
+//! Ids longer than 23 characters are printed on their own line, followed by
+//! an indented `time:` line.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use once_cell::sync::OnceCell;
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::{
+    units::{throughput_unit, time_unit},
+    Benchmark, Interval, Measurement, ParseError,
+};
+
+/// A line of Criterion.rs output that belongs to a benchmark.
+#[derive(Debug, PartialEq)]
+pub(crate) enum Line<'a> {
+    /// `id time: [..]`, the id is empty if it was printed on the previous line
+    Time { id: &'a str, interval: Interval },
+    /// `thrpt: [..]`
+    Throughput(Measurement),
+    /// `change:` and the relative `time:`/`thrpt:` changes below it
+    Change,
+}
+
+fn get_criterion_regex() -> &'static Regex {
+    static INSTANCE: OnceCell<Regex> = OnceCell::new();
+    INSTANCE.get_or_init(|| {
+        Regex::new(
+            r##"(?x)
+        ^(?P<id>.*?)\s*                             # fib 20
+        (?P<kind>time|thrpt|change):                # time:
+        \s*(?:\[(?P<values>[^\]]*)\])?              # [26.029 µs 26.251 µs 26.505 µs]
+    "##,
+        )
+        .unwrap()
+    })
+}
+
+/// Parses a line of Criterion.rs output, `None` if it is not part of a benchmark.
+pub(crate) fn parse_line(line: &str) -> Option<Result<Line<'_>, ParseError>> {
+    let caps = get_criterion_regex().captures(line)?;
+    let id = caps.name("id").unwrap().as_str();
+    let kind = &caps["kind"];
+    let values = match caps.name("values") {
+        // The `change:` header of a benchmark with throughput
+        None if kind == "change" && id.is_empty() => return Some(Ok(Line::Change)),
+        None => return None,
+        Some(values) => values,
+    };
+    if kind == "change" || values.as_str().contains('%') {
+        return Some(Ok(Line::Change));
+    }
+    if kind == "thrpt" && !id.is_empty() {
+        return None;
+    }
+    let to_unit = match kind {
+        "time" => time_unit,
+        _ => throughput_unit,
+    };
+    let (interval, unit) = match parse_interval(values.as_str(), to_unit) {
+        Some(interval) => interval,
+        None => {
+            return Some(Err(ParseError::MalformedInterval {
+                span: values.range(),
+            }))
+        }
+    };
+    Some(Ok(match kind {
+        "time" => Line::Time { id, interval },
+        _ => Line::Throughput(
+            Measurement::new("throughput", interval.estimate, unit).with_interval(interval),
+        ),
+    }))
+}
+
+/// Parses `lower unit estimate unit upper unit`, converted with `to_unit`.
+fn parse_interval(
+    values: &str,
+    to_unit: fn(&str) -> Option<(f64, &'static str)>,
+) -> Option<(Interval, &'static str)> {
+    let tokens: Vec<_> = values.split_whitespace().collect();
+    if tokens.len() != 6 {
+        return None;
+    }
+    let value = |index: usize| -> Option<(f64, &'static str)> {
+        let value: f64 = tokens[index].parse().ok()?;
+        let (factor, unit) = to_unit(tokens[index + 1])?;
+        Some((value * factor, unit))
+    };
+    let (lower, unit) = value(0)?;
+    let interval = Interval {
+        lower,
+        estimate: value(2)?.0,
+        upper: value(4)?.0,
+    };
+    Some((interval, unit))
+}
+
+/// A benchmark loaded from a `target/criterion` directory.
+///
+/// All durations are in ns.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CriterionBenchmark {
+    /// e.g. group/function/value
+    pub full_id: String,
+    pub group_id: String,
+    pub function_id: Option<String>,
+    pub value_str: Option<String>,
+    pub mean: Interval,
+    pub median: Interval,
+    pub std_dev: Interval,
+    /// Only available for linear sampling
+    pub slope: Option<Interval>,
+    /// Bytes processed per iteration, if configured
+    pub bytes: Option<u64>,
+    /// Elements processed per iteration, if configured
+    pub elements: Option<u64>,
+    /// Duration per iteration of each sample, empty without a `sample.json`
+    pub samples: Vec<f64>,
+}
+
+impl CriterionBenchmark {
+    /// The estimate Criterion.rs reports as `time:`, the slope if available.
+    pub fn typical(&self) -> Interval {
+        self.slope.unwrap_or(self.mean)
+    }
+}
+
+impl From<CriterionBenchmark> for Benchmark {
+    /// The typical estimate becomes the `time`, the other estimates are kept as
+    /// `mean`, `median` and `std_dev` measurements.
+    fn from(criterion: CriterionBenchmark) -> Benchmark {
+        let typical = criterion.typical();
+        let estimate = |metric: &str, interval: Interval| {
+            Measurement::new(metric, interval.estimate, "ns").with_interval(interval)
+        };
+        let mut bench = Benchmark::from_time(
+            criterion.full_id,
+            estimate("time", typical).with_dispersion(typical.upper - typical.lower),
+        );
+        bench.measurements.extend([
+            estimate("mean", criterion.mean),
+            estimate("median", criterion.median),
+            estimate("std_dev", criterion.std_dev),
+        ]);
+        bench.samples = criterion.samples;
+        let per_second = |count: u64| {
+            let scale = |ns: f64| count as f64 * 1e9 / ns;
+            Interval {
+                lower: scale(typical.upper),
+                estimate: scale(typical.estimate),
+                upper: scale(typical.lower),
+            }
+        };
+        if let Some(bytes) = criterion.bytes {
+            let interval = per_second(bytes);
+            let mb = |bytes: f64| bytes / 1e6;
+            bench.push_throughput(
+                Measurement::new("throughput", mb(interval.estimate), "MB/s").with_interval(
+                    Interval {
+                        lower: mb(interval.lower),
+                        estimate: mb(interval.estimate),
+                        upper: mb(interval.upper),
+                    },
+                ),
+            );
+        }
+        if let Some(elements) = criterion.elements {
+            let interval = per_second(elements);
+            bench.push_throughput(
+                Measurement::new("throughput", interval.estimate, "elem/s").with_interval(interval),
+            );
+        }
+        bench
+    }
+}
+
+#[derive(Deserialize)]
+struct BenchmarkJson {
+    group_id: String,
+    function_id: Option<String>,
+    value_str: Option<String>,
+    full_id: String,
+    throughput: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct EstimatesJson {
+    mean: EstimateJson,
+    median: EstimateJson,
+    std_dev: EstimateJson,
+    slope: Option<EstimateJson>,
+}
+
+#[derive(Deserialize)]
+struct EstimateJson {
+    confidence_interval: ConfidenceIntervalJson,
+    point_estimate: f64,
+}
+
+#[derive(Deserialize)]
+struct ConfidenceIntervalJson {
+    lower_bound: f64,
+    upper_bound: f64,
+}
+
+#[derive(Deserialize)]
+struct SampleJson {
+    iters: Vec<f64>,
+    times: Vec<f64>,
+}
+
+impl From<EstimateJson> for Interval {
+    fn from(estimate: EstimateJson) -> Interval {
+        Interval {
+            lower: estimate.confidence_interval.lower_bound,
+            estimate: estimate.point_estimate,
+            upper: estimate.confidence_interval.upper_bound,
+        }
+    }
+}
+
+/// Loads the latest results of all benchmarks in a `target/criterion` directory,
+/// sorted by id.
+pub fn load_dir<P: AsRef<Path>>(path: P) -> io::Result<Vec<CriterionBenchmark>> {
+    let mut dirs = Vec::new();
+    find_new_dirs(path.as_ref(), &mut dirs)?;
+    let mut benchmarks = dirs
+        .iter()
+        .map(|dir| load_new_dir(dir))
+        .collect::<io::Result<Vec<_>>>()?;
+    benchmarks.sort_by(|a, b| a.full_id.cmp(&b.full_id));
+    Ok(benchmarks)
+}
+
+/// Collects all `new` directories containing a `benchmark.json`.
+fn find_new_dirs(dir: &Path, dirs: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if !path.is_dir() {
+            continue;
+        }
+        if path.ends_with("new") && path.join("benchmark.json").is_file() {
+            dirs.push(path);
+        } else {
+            find_new_dirs(&path, dirs)?;
+        }
+    }
+    Ok(())
+}
+
+fn load_new_dir(dir: &Path) -> io::Result<CriterionBenchmark> {
+    let benchmark: BenchmarkJson = read_json(&dir.join("benchmark.json"))?;
+    let estimates: EstimatesJson = read_json(&dir.join("estimates.json"))?;
+    let throughput = |kinds: &[&str]| {
+        let throughput = benchmark.throughput.as_ref()?;
+        kinds
+            .iter()
+            .find_map(|kind| throughput.get(kind))
+            .and_then(|count| count.as_u64())
+    };
+    let bytes = throughput(&["Bytes", "BytesDecimal"]);
+    let elements = throughput(&["Elements"]);
+    let sample_path = dir.join("sample.json");
+    let samples = if sample_path.is_file() {
+        let sample: SampleJson = read_json(&sample_path)?;
+        sample
+            .times
+            .iter()
+            .zip(&sample.iters)
+            .map(|(time, iters)| time / iters)
+            .collect()
+    } else {
+        Vec::new()
+    };
+    Ok(CriterionBenchmark {
+        full_id: benchmark.full_id,
+        group_id: benchmark.group_id,
+        function_id: benchmark.function_id,
+        value_str: benchmark.value_str,
+        mean: estimates.mean.into(),
+        median: estimates.median.into(),
+        std_dev: estimates.std_dev.into(),
+        slope: estimates.slope.map(Interval::from),
+        bytes,
+        elements,
+        samples,
+    })
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> io::Result<T> {
+    let file = io::BufReader::new(fs::File::open(path)?);
+    serde_json::from_reader(file).map_err(|err| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {}", path.display(), err),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_criterion_line_test() {
+        assert_eq!(
+            parse_line("fib 20                  time:   [26.029 µs 26.251 µs 26.505 us]"),
+            Some(Ok(Line::Time {
+                id: "fib 20",
+                interval: Interval {
+                    lower: 26029.0,
+                    estimate: 26251.0,
+                    upper: 26505.0
+                }
+            }))
+        );
+        assert_eq!(
+            parse_line("                        thrpt:  [1.0 GiB/s 1.5 GB/s 2.0 TB/s]"),
+            Some(Ok(Line::Throughput(
+                Measurement::new("throughput", 1500.0, "MB/s").with_interval(Interval {
+                    lower: 1073.741824,
+                    estimate: 1500.0,
+                    upper: 2000000.0
+                })
+            )))
+        );
+        assert_eq!(
+            parse_line("                        thrpt:  [1.0 Kelem/s 1.5 Kelem/s 2.0 Kelem/s]"),
+            Some(Ok(Line::Throughput(
+                Measurement::new("throughput", 1500.0, "elem/s").with_interval(Interval {
+                    lower: 1000.0,
+                    estimate: 1500.0,
+                    upper: 2000.0
+                })
+            )))
+        );
+        assert_eq!(
+            parse_line("                        change: [-1.2% +0.3% +1.8%] (p = 0.71 > 0.05)"),
+            Some(Ok(Line::Change))
+        );
+        assert_eq!(
+            parse_line("                        time:   [1.0 ms 1.5 ms]"),
+            Some(Err(ParseError::MalformedInterval { span: 33..46 }))
+        );
+        assert_eq!(parse_line("Found 11 outliers among 100 measurements"), None);
+    }
+
+    #[test]
+    fn load_dir_test() {
+        let root = std::env::temp_dir().join(format!("criterion_load_dir_{}", std::process::id()));
+        let new_dir = root.join("fib").join("20").join("new");
+        fs::create_dir_all(&new_dir).unwrap();
+        fs::create_dir_all(root.join("report")).unwrap();
+        fs::write(
+            new_dir.join("benchmark.json"),
+            r#"{"group_id":"fib","function_id":null,"value_str":"20","throughput":{"Bytes":1000},
+                "full_id":"fib/20","directory_name":"fib/20","title":"fib/20"}"#,
+        )
+        .unwrap();
+        let estimate = |estimate: f64| {
+            format!(
+                r#"{{"confidence_interval":{{"confidence_level":0.95,"lower_bound":{},"upper_bound":{}}},
+                    "point_estimate":{},"standard_error":1.0}}"#,
+                estimate - 1.0,
+                estimate + 1.0,
+                estimate
+            )
+        };
+        fs::write(
+            new_dir.join("estimates.json"),
+            format!(
+                r#"{{"mean":{},"median":{},"median_abs_dev":{},"slope":null,"std_dev":{}}}"#,
+                estimate(500.0),
+                estimate(400.0),
+                estimate(3.0),
+                estimate(5.0)
+            ),
+        )
+        .unwrap();
+        fs::write(
+            new_dir.join("sample.json"),
+            r#"{"sampling_mode":"Flat","iters":[10.0,10.0],"times":[4990.0,5010.0]}"#,
+        )
+        .unwrap();
+
+        let benchmarks = load_dir(&root).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(benchmarks.len(), 1);
+        let criterion = benchmarks[0].clone();
+        assert_eq!(criterion.group_id, "fib");
+        assert_eq!(criterion.function_id, None);
+        assert_eq!(criterion.value_str.as_deref(), Some("20"));
+        assert_eq!(criterion.median.estimate, 400.0);
+        assert_eq!(criterion.std_dev.upper, 6.0);
+
+        let bench = Benchmark::from(criterion);
+        assert_eq!(bench.name, "fib/20");
+        assert_eq!(bench.ns, 500.0);
+        assert_eq!(bench.variance, 2.0);
+        assert_eq!(bench.throughput, Some(2000));
+        assert_eq!(bench.measurement("median").unwrap().value, 400.0);
+        assert_eq!(bench.samples, [499.0, 501.0]);
+        let throughput = bench.measurement("throughput").unwrap().interval.unwrap();
+        assert_eq!(throughput.lower.round(), 1996.0);
+        assert_eq!(throughput.upper.round(), 2004.0);
+    }
+}
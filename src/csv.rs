@@ -0,0 +1,121 @@
+//! RFC 4180 CSV writing, used by `cargobench_to_csv`.
+//!
+//! Fields containing the delimiter, a quote or a line break are enclosed in
+//! double quotes, with quotes doubled. Records end with `\n` instead of the
+//! RFC's `\r\n`, which every common reader accepts and line based shell
+//! scripts expect.
+
+use std::{
+    borrow::Cow,
+    io::{self, Write},
+};
+
+/// When fields are enclosed in double quotes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Quoting {
+    /// Only fields that contain the delimiter, a quote or a line break
+    #[default]
+    Necessary,
+    /// Every field
+    Always,
+}
+
+/// Writes records of fields as CSV.
+pub struct CsvWriter<W> {
+    writer: W,
+    delimiter: char,
+    quoting: Quoting,
+}
+
+impl<W: Write> CsvWriter<W> {
+    pub fn new(writer: W) -> Self {
+        CsvWriter {
+            writer,
+            delimiter: ',',
+            quoting: Quoting::Necessary,
+        }
+    }
+
+    /// Sets the field delimiter, `,` by default.
+    pub fn delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Sets when fields are quoted, [`Quoting::Necessary`] by default.
+    pub fn quoting(mut self, quoting: Quoting) -> Self {
+        self.quoting = quoting;
+        self
+    }
+
+    /// Writes one record, escaping each field.
+    pub fn write_record<I, S>(&mut self, fields: I) -> io::Result<()>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        for (i, field) in fields.into_iter().enumerate() {
+            if i > 0 {
+                write!(self.writer, "{}", self.delimiter)?;
+            }
+            let field = escape(field.as_ref(), self.delimiter, self.quoting);
+            self.writer.write_all(field.as_bytes())?;
+        }
+        self.writer.write_all(b"\n")
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Escapes a single field, borrowing it when no quoting is needed.
+pub fn escape(field: &str, delimiter: char, quoting: Quoting) -> Cow<'_, str> {
+    let needs_quotes = quoting == Quoting::Always || field.contains([delimiter, '"', '\n', '\r']);
+    if !needs_quotes {
+        return Cow::Borrowed(field);
+    }
+    Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(writer: CsvWriter<Vec<u8>>, records: &[&[&str]]) -> String {
+        let mut writer = writer;
+        for record in records {
+            writer.write_record(*record).unwrap();
+        }
+        String::from_utf8(writer.into_inner()).unwrap()
+    }
+
+    #[test]
+    fn escape_test() {
+        let records: &[&[&str]] = &[
+            &["name", "ns"],
+            &["group/size=1,2", "10"],
+            &["say \"hi\"", ""],
+            &["multi\nline", "1.5"],
+        ];
+        assert_eq!(
+            write(CsvWriter::new(Vec::new()), records),
+            "name,ns\n\"group/size=1,2\",10\n\"say \"\"hi\"\"\",\n\"multi\nline\",1.5\n"
+        );
+        assert_eq!(
+            write(CsvWriter::new(Vec::new()).delimiter('\t'), &records[..2]),
+            "name\tns\ngroup/size=1,2\t10\n"
+        );
+        assert_eq!(
+            write(
+                CsvWriter::new(Vec::new()).quoting(Quoting::Always),
+                &records[..1]
+            ),
+            "\"name\",\"ns\"\n"
+        );
+    }
+}
@@ -0,0 +1,165 @@
+//! Parsing of divan's benchmark tables.
+//!
+//! ```text
+//! example             fastest       │ slowest       │ median        │ mean          │ samples │ iters
+//! ├─ fibonacci                      │               │               │               │         │
+//! │  ├─ 0             0.159 ns      │ 0.172 ns      │ 0.162 ns      │ 0.162 ns      │ 100     │ 3276800
+//! │  ╰─ 5             6.834 ns      │ 7.568 ns      │ 6.893 ns      │ 6.928 ns      │ 100     │ 102400
+//! ╰─ copy             227.6 ns      │ 294.7 ns      │ 236.6 ns      │ 240.9 ns      │ 100     │ 1600
+//!                     4.497 GB/s    │ 3.474 GB/s    │ 4.327 GB/s    │ 4.25 GB/s     │         │
+//! ```
+//!
+//! Each level of the tree is indented by three characters; the root is the
+//! name of the bench target.
+
+use once_cell::sync::OnceCell;
+use regex::Regex;
+
+use crate::{
+    units::{parse_quantity, throughput_unit, time_unit},
+    Measurement, ParseError,
+};
+
+/// A line of a divan table.
+#[derive(Debug, PartialEq)]
+pub(crate) enum Line<'a> {
+    /// The header, starting a table
+    Header,
+    /// A node of the tree, `timings` is `None` for modules and functions with arguments
+    Row {
+        depth: usize,
+        name: &'a str,
+        timings: Option<Timings>,
+    },
+    /// Counters of the row above, the throughput if it counts bytes or items
+    Counter(Option<Measurement>),
+}
+
+/// The timings of a benchmark in ns.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Timings {
+    pub fastest: f64,
+    pub slowest: f64,
+    pub median: f64,
+    pub mean: f64,
+}
+
+fn get_header_regex() -> &'static Regex {
+    static INSTANCE: OnceCell<Regex> = OnceCell::new();
+    INSTANCE.get_or_init(|| {
+        Regex::new(r"^\S+\s+fastest\s+│\s+slowest\s+│\s+median\s+│\s+mean\s+│").unwrap()
+    })
+}
+
+fn get_row_regex() -> &'static Regex {
+    static INSTANCE: OnceCell<Regex> = OnceCell::new();
+    INSTANCE.get_or_init(|| {
+        Regex::new(
+            r##"(?x)
+        ^(?P<indent>(?:│\ \ |\ \ \ )*)[├╰]─\ # │  ├─
+        (?P<name>.+?)                       # fibonacci
+        (?:\s{2,}(?P<columns>.*))?$         # 0.159 ns │ 0.172 ns │ ...
+    "##,
+        )
+        .unwrap()
+    })
+}
+
+/// Parses a line of a divan table, `None` if it is not part of one.
+pub(crate) fn parse_line(line: &str) -> Option<Result<Line<'_>, ParseError>> {
+    if get_header_regex().is_match(line) {
+        return Some(Ok(Line::Header));
+    }
+    if let Some(caps) = get_row_regex().captures(line) {
+        let depth = caps["indent"].chars().count() / 3;
+        let name = caps.name("name").unwrap().as_str();
+        let columns = match caps.name("columns") {
+            Some(columns) if !columns.as_str().starts_with('│') => columns,
+            _ => {
+                return Some(Ok(Line::Row {
+                    depth,
+                    name,
+                    timings: None,
+                }))
+            }
+        };
+        let timings = parse_timings(columns.as_str()).ok_or(ParseError::MalformedTiming {
+            span: columns.range(),
+        });
+        return Some(timings.map(|timings| Line::Row {
+            depth,
+            name,
+            timings: Some(timings),
+        }));
+    }
+    let columns = line.trim_start_matches(['│', ' ']);
+    if line.starts_with(['│', ' ']) && columns.contains('│') {
+        let median = columns.split('│').nth(2)?;
+        let throughput = parse_quantity(median, throughput_unit)
+            .map(|(value, unit)| Measurement::new("throughput", value, unit));
+        return Some(Ok(Line::Counter(throughput)));
+    }
+    None
+}
+
+fn parse_timings(columns: &str) -> Option<Timings> {
+    let mut columns = columns.split('│');
+    let mut next = || Some(parse_quantity(columns.next()?, time_unit)?.0);
+    Some(Timings {
+        fastest: next()?,
+        slowest: next()?,
+        median: next()?,
+        mean: next()?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_divan_line_test() {
+        assert_eq!(
+            parse_line("example   fastest       │ slowest       │ median        │ mean          │ samples │ iters"),
+            Some(Ok(Line::Header))
+        );
+        assert_eq!(
+            parse_line("├─ fibonacci                      │               │               │               │         │"),
+            Some(Ok(Line::Row {
+                depth: 0,
+                name: "fibonacci",
+                timings: None
+            }))
+        );
+        assert_eq!(
+            parse_line("│  ╰─ 5             6.834 ns      │ 7.568 µs      │ 6.893 ns      │ 6.928 ns      │ 100     │ 102400"),
+            Some(Ok(Line::Row {
+                depth: 1,
+                name: "5",
+                timings: Some(Timings {
+                    fastest: 6.834,
+                    slowest: 7568.0,
+                    median: 6.893,
+                    mean: 6.928
+                })
+            }))
+        );
+        assert_eq!(
+            parse_line("                    4.497 GB/s    │ 3.474 GB/s    │ 4.327 GB/s    │ 4.25 GB/s     │         │"),
+            Some(Ok(Line::Counter(Some(Measurement::new(
+                "throughput",
+                4327.0,
+                "MB/s"
+            )))))
+        );
+        assert_eq!(
+            parse_line("│                   1.5 Mitem/s   │ 1.5 Mitem/s   │ 1.5 Mitem/s   │ 1.5 Mitem/s   │         │"),
+            Some(Ok(Line::Counter(Some(Measurement::new(
+                "throughput",
+                1_500_000.0,
+                "elem/s"
+            )))))
+        );
+        assert_eq!(parse_line("Timer precision: 41 ns"), None);
+    }
+}
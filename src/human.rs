@@ -0,0 +1,202 @@
+//! Human-readable formatting of durations, e.g. `95,653,541 ns` as `95.65 ms`.
+
+use std::{fmt, time::Duration};
+
+/// A unit [`HumanDuration`] can be displayed in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimeUnit {
+    Ns,
+    Us,
+    Ms,
+    S,
+}
+
+impl TimeUnit {
+    const ALL: [TimeUnit; 4] = [TimeUnit::Ns, TimeUnit::Us, TimeUnit::Ms, TimeUnit::S];
+
+    /// Nanoseconds per unit.
+    pub fn ns(self) -> f64 {
+        match self {
+            TimeUnit::Ns => 1.0,
+            TimeUnit::Us => 1e3,
+            TimeUnit::Ms => 1e6,
+            TimeUnit::S => 1e9,
+        }
+    }
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            TimeUnit::Ns => "ns",
+            TimeUnit::Us => "µs",
+            TimeUnit::Ms => "ms",
+            TimeUnit::S => "s",
+        }
+    }
+}
+
+/// Displays a duration in nanoseconds, by default with 2 decimals in the
+/// largest unit that keeps the value at least 1.
+///
+/// ```
+/// use rust_bench_parser::human::{HumanDuration, TimeUnit};
+///
+/// assert_eq!(HumanDuration::from_ns(95_653_541.0).to_string(), "95.65 ms");
+/// let exact = HumanDuration::from_ns(95_653_541.0)
+///     .unit(TimeUnit::Ns)
+///     .precision(0)
+///     .thousands_separators(true);
+/// assert_eq!(exact.to_string(), "95,653,541 ns");
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HumanDuration {
+    ns: f64,
+    precision: usize,
+    unit: Option<TimeUnit>,
+    thousands_separators: bool,
+}
+
+impl HumanDuration {
+    pub fn from_ns(ns: f64) -> Self {
+        HumanDuration {
+            ns,
+            precision: 2,
+            unit: None,
+            thousands_separators: false,
+        }
+    }
+
+    /// Sets the number of decimals, 2 by default.
+    pub fn precision(mut self, precision: usize) -> Self {
+        self.precision = precision;
+        self
+    }
+
+    /// Displays the duration in `unit` instead of scaling it automatically.
+    pub fn unit(mut self, unit: TimeUnit) -> Self {
+        self.unit = Some(unit);
+        self
+    }
+
+    /// Groups the integer digits by thousands with `,`.
+    pub fn thousands_separators(mut self, thousands_separators: bool) -> Self {
+        self.thousands_separators = thousands_separators;
+        self
+    }
+
+    /// The unit the duration is displayed in.
+    fn display_unit(&self) -> TimeUnit {
+        if let Some(unit) = self.unit {
+            return unit;
+        }
+        // Compares the rounded value, so that 999.999 ns becomes 1.00 µs
+        let factor = 10f64.powi(self.precision as i32);
+        TimeUnit::ALL
+            .into_iter()
+            .rev()
+            .find(|unit| (self.ns.abs() / unit.ns() * factor).round() >= factor)
+            .unwrap_or(TimeUnit::Ns)
+    }
+}
+
+impl From<Duration> for HumanDuration {
+    fn from(duration: Duration) -> Self {
+        HumanDuration::from_ns(duration.as_nanos() as f64)
+    }
+}
+
+impl fmt::Display for HumanDuration {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let unit = self.display_unit();
+        let value = format!("{:.*}", self.precision, self.ns / unit.ns());
+        if self.thousands_separators {
+            write!(f, "{} {}", group_thousands(&value), unit.symbol())
+        } else {
+            write!(f, "{} {}", value, unit.symbol())
+        }
+    }
+}
+
+/// Inserts `,` between groups of three integer digits of a formatted number,
+/// e.g. `-1234567.5` becomes `-1,234,567.5`.
+pub fn group_thousands(number: &str) -> String {
+    let (sign, unsigned) = match number.strip_prefix('-') {
+        Some(unsigned) => ("-", unsigned),
+        None => ("", number),
+    };
+    let int_len = unsigned
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(unsigned.len());
+    let (int, rest) = unsigned.split_at(int_len);
+    let mut grouped = String::with_capacity(number.len() + int_len / 3);
+    grouped.push_str(sign);
+    for (i, digit) in int.chars().enumerate() {
+        if i > 0 && (int_len - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped.push_str(rest);
+    grouped
+}
+
+/// Formats a spread relative to a value as a percentage, e.g. `±1.2%`, or
+/// `None` for a value of 0.
+pub fn relative_percent(spread: f64, value: f64, precision: usize) -> Option<String> {
+    if value == 0.0 {
+        return None;
+    }
+    Some(format!("±{:.*}%", precision, spread / value.abs() * 100.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn human_duration_test() {
+        let human = |ns: f64| HumanDuration::from_ns(ns).to_string();
+        assert_eq!(human(0.0), "0.00 ns");
+        assert_eq!(human(12.5), "12.50 ns");
+        assert_eq!(human(999.999), "1.00 µs");
+        assert_eq!(human(1_234.0), "1.23 µs");
+        assert_eq!(human(95_653_541.0), "95.65 ms");
+        assert_eq!(human(3_500_000_000.0), "3.50 s");
+        assert_eq!(human(-2_000.0), "-2.00 µs");
+
+        assert_eq!(
+            HumanDuration::from_ns(1_234_567_000_000.0)
+                .precision(1)
+                .thousands_separators(true)
+                .to_string(),
+            "1,234.6 s"
+        );
+        assert_eq!(
+            HumanDuration::from(Duration::from_micros(1_500))
+                .precision(0)
+                .to_string(),
+            "2 ms"
+        );
+        assert_eq!(
+            HumanDuration::from_ns(1_500.0)
+                .unit(TimeUnit::Ns)
+                .precision(0)
+                .to_string(),
+            "1500 ns"
+        );
+    }
+
+    #[test]
+    fn group_thousands_test() {
+        assert_eq!(group_thousands("0"), "0");
+        assert_eq!(group_thousands("123"), "123");
+        assert_eq!(group_thousands("1234"), "1,234");
+        assert_eq!(group_thousands("-95653541.25"), "-95,653,541.25");
+        assert_eq!(group_thousands("100000"), "100,000");
+    }
+
+    #[test]
+    fn relative_percent_test() {
+        assert_eq!(relative_percent(5.0, 200.0, 1), Some("±2.5%".to_string()));
+        assert_eq!(relative_percent(5.0, 0.0, 1), None);
+    }
+}
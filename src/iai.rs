@@ -0,0 +1,67 @@
+//! Parsing of iai and iai-callgrind output.
+//!
+//! ```text
+//! bench_fibonacci_short
+//!   Instructions:                1735|1735            (No change)
+//!   L1 Hits:                     2359|2359            (No change)
+//!   L2 Hits:                        0|0               (No change)
+//!   RAM Hits:                       3|3               (No change)
+//!   Total read+write:            2362|2362            (No change)
+//!   Estimated Cycles:            2464|2464            (No change)
+//! ```
+//!
+//! iai prints the same without the `|old` value of the previous run.
+
+use once_cell::sync::OnceCell;
+use regex::Regex;
+
+use crate::{parse_field, Field, Measurement, ParseError};
+
+fn get_counter_regex() -> &'static Regex {
+    static INSTANCE: OnceCell<Regex> = OnceCell::new();
+    INSTANCE.get_or_init(|| {
+        Regex::new(
+            r##"(?x)
+        ^\s+(?P<name>[A-Za-z][A-Za-z0-9\ +]*):      # Instructions:
+        \s+(?P<value>[0-9]+)                        # 1735
+        (?:\|\S+)?                                  # |1735
+        (?:\s+\([^)]*\))?\s*$                       # (No change)
+    "##,
+        )
+        .unwrap()
+    })
+}
+
+/// Parses an indented counter line, `None` if it is none.
+pub(crate) fn parse_line(line: &str) -> Option<Result<Measurement, ParseError>> {
+    let caps = get_counter_regex().captures(line)?;
+    Some(
+        parse_field(Field::Counter, caps.name("value").unwrap())
+            .map(|value| Measurement::new(&caps["name"], value as f64, "")),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_iai_line_test() {
+        assert_eq!(
+            parse_line("  Total read+write:            2362|2362            (No change)"),
+            Some(Ok(Measurement::new("Total read+write", 2362.0, "")))
+        );
+        assert_eq!(
+            parse_line("  L1 Accesses:                 2364 (+0.042337%)"),
+            Some(Ok(Measurement::new("L1 Accesses", 2364.0, "")))
+        );
+        assert!(matches!(
+            parse_line("  Instructions:   99999999999999999999999"),
+            Some(Err(ParseError::NumberOverflow {
+                field: Field::Counter,
+                ..
+            }))
+        ));
+        assert_eq!(parse_line("bench_fibonacci_short"), None);
+    }
+}
@@ -0,0 +1,97 @@
+//! Lazy parsing of benchmarks as the output arrives.
+
+use std::io::{self, BufRead, Write};
+
+use crate::{Benchmark, Event, Parser};
+
+/// Iterator over the benchmarks of a buffered reader, parsed as lines arrive.
+///
+/// Optionally forwards every line read to a writer, so that live `cargo bench`
+/// output can still be watched while it is parsed.
+pub struct BenchIter<B, W = io::Sink> {
+    buffer: B,
+    tee: W,
+    parser: Parser,
+    line: String,
+    finished: bool,
+}
+
+impl<B: BufRead> BenchIter<B> {
+    pub fn new(buffer: B) -> Self {
+        Self::tee(buffer, io::sink())
+    }
+}
+
+impl<B: BufRead, W: Write> BenchIter<B, W> {
+    /// Like [`BenchIter::new`], forwarding every line read to `tee`.
+    pub fn tee(buffer: B, tee: W) -> Self {
+        BenchIter {
+            buffer,
+            tee,
+            parser: Parser::new(),
+            line: String::new(),
+            finished: false,
+        }
+    }
+
+    /// Reads and parses the next line, returns false at the end of the input.
+    fn read_line(&mut self) -> io::Result<bool> {
+        self.line.clear();
+        if self.buffer.read_line(&mut self.line)? == 0 {
+            self.parser.finish();
+            return Ok(false);
+        }
+        self.tee.write_all(self.line.as_bytes())?;
+        self.tee.flush()?;
+        let line = self.line.trim_end_matches(['\n', '\r']);
+        let _ = self.parser.push_line(line);
+        Ok(true)
+    }
+}
+
+impl<B: BufRead, W: Write> Iterator for BenchIter<B, W> {
+    type Item = io::Result<Benchmark>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            while let Some(event) = self.parser.next_event() {
+                if let Event::Benchmark(bench) = event {
+                    return Some(Ok(bench));
+                }
+            }
+            if self.finished {
+                return None;
+            }
+            match self.read_line() {
+                Ok(more) => self.finished = !more,
+                Err(err) => {
+                    self.finished = true;
+                    return Some(Err(err));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::BufReader;
+
+    use super::*;
+
+    #[test]
+    fn bench_iter_test() {
+        let data = "running 2 tests\r\n\
+                    test a::first ... bench: 100 ns/iter (+/- 3)\n\
+                    Benchmarking b\n\
+                    b    time:   [1.0 ns 2.0 ns 3.0 ns]";
+        let mut output = Vec::new();
+        let mut iter = BenchIter::tee(BufReader::new(data.as_bytes()), &mut output);
+
+        assert_eq!(iter.next().unwrap().unwrap().name, "a::first");
+        assert_eq!(iter.next().unwrap().unwrap().name, "b");
+        assert!(iter.next().is_none());
+        assert!(iter.next().is_none());
+        assert_eq!(output, data.as_bytes());
+    }
+}
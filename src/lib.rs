@@ -1,25 +1,244 @@
 use std::{
+    borrow::Cow,
     cmp,
+    collections::VecDeque,
+    error, fmt,
     io::{self, BufRead},
+    num::IntErrorKind,
+    ops::Range,
     str::FromStr,
+    time::Duration,
 };
 
 use once_cell::sync::OnceCell;
 use regex::Regex;
 
+mod aggregate;
+mod cargo;
+mod compare;
+pub mod criterion;
+pub mod csv;
+mod divan;
+pub mod human;
+mod iai;
+mod iter;
+mod libtest;
+mod libtest_json;
+pub mod output;
+mod run;
+pub mod select;
+mod stats;
+mod units;
+
+pub use aggregate::{aggregate, Aggregate};
+pub use compare::{
+    compare, compare_with, parse_percent, Change, Comparison, MatchOptions, Thresholds, Verdict,
+};
+pub use iter::BenchIter;
+pub use run::{parse_benchmarks, parse_runs, BenchRun, RunWarning, TestSummary};
+
 /// All extractable data from a single micro-benchmark.
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Benchmark {
     /// e.g. mod::test_name
     pub name: String,
     /// e.g. test_name
     pub shortname: String,
     /// The benchmarks duration
-    pub ns: u64,
+    pub ns: f64,
     /// The benchmarks variance
-    pub variance: u64,
+    pub variance: f64,
     /// Throughput of the benchmark if available
     pub throughput: Option<u64>,
+    /// Everything measured for the benchmark.
+    ///
+    /// The `time` measurement mirrors `ns` and `variance`, a `throughput` in
+    /// MB/s mirrors `throughput`. Benchmarks measured by counters only, like
+    /// iai, have no `time` and a `ns` and `variance` of 0.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub measurements: Vec<Measurement>,
+    /// Durations per iteration in ns of each sample, if the source keeps them
+    /// (Criterion.rs directories)
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub samples: Vec<f64>,
+    /// The bench binary the benchmark was run from, if cargo's output was parsed
+    pub source: Option<BenchSource>,
+    /// Whether the benchmark ran; ignored and failed benchmarks have no measurements
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub outcome: BenchOutcome,
+}
+
+impl Benchmark {
+    /// A benchmark without any measurements yet.
+    pub(crate) fn from_name(name: String) -> Benchmark {
+        let shortname = shortname(&name).to_string();
+        Benchmark {
+            name,
+            shortname,
+            ns: 0.0,
+            variance: 0.0,
+            throughput: None,
+            measurements: Vec::new(),
+            samples: Vec::new(),
+            source: None,
+            outcome: BenchOutcome::Ok,
+        }
+    }
+
+    /// A benchmark taking `time`, which provides `ns` and `variance`.
+    pub(crate) fn from_time(name: String, time: Measurement) -> Benchmark {
+        Benchmark {
+            ns: time.value,
+            variance: time.dispersion.unwrap_or(0.0),
+            measurements: vec![time],
+            ..Benchmark::from_name(name)
+        }
+    }
+
+    /// A benchmark as reported by libtest.
+    pub(crate) fn from_libtest(
+        name: String,
+        ns: f64,
+        variance: f64,
+        throughput: Option<u64>,
+    ) -> Benchmark {
+        let time = Measurement::new("time", ns, "ns").with_dispersion(variance);
+        let mut bench = Benchmark::from_time(name, time);
+        if let Some(throughput) = throughput {
+            bench.push_throughput(Measurement::new("throughput", throughput as f64, "MB/s"));
+        }
+        bench
+    }
+
+    /// Adds a throughput, which provides `throughput` if it is in MB/s.
+    pub(crate) fn push_throughput(&mut self, throughput: Measurement) {
+        if throughput.unit == "MB/s" {
+            self.throughput = Some(throughput.value.round() as u64);
+        }
+        self.measurements.push(throughput);
+    }
+
+    /// The first measurement of `metric`.
+    pub fn measurement(&self, metric: &str) -> Option<&Measurement> {
+        self.measurements
+            .iter()
+            .find(|measurement| measurement.metric == metric)
+    }
+
+    /// Confidence interval of the duration in ns, if reported (Criterion.rs).
+    pub fn interval(&self) -> Option<Interval> {
+        self.measurement("time")?.interval
+    }
+
+    /// The duration, `None` if the benchmark did not run, has no time, e.g.
+    /// iai counters, or if `ns` is negative or too large for a [`Duration`].
+    /// For display see [`human::HumanDuration`].
+    pub fn duration(&self) -> Option<Duration> {
+        if self.outcome != BenchOutcome::Ok {
+            return None;
+        }
+        self.measurement("time")?;
+        Duration::try_from_secs_f64(self.ns / 1e9).ok()
+    }
+
+    /// The variance relative to the duration in percent, `None` if the
+    /// duration is 0, e.g. for counters or benchmarks that did not run.
+    pub fn relative_variance(&self) -> Option<f64> {
+        if self.ns == 0.0 {
+            return None;
+        }
+        Some(self.variance / self.ns * 100.0)
+    }
+}
+
+/// A single measured quantity of a benchmark.
+///
+/// Common metrics are `time` in ns and `throughput` in MB/s or elem/s. Other
+/// metrics are named by their source, e.g. `median` for Criterion.rs
+/// estimates or `Instructions` for iai counters, which have an empty unit.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Measurement {
+    pub metric: String,
+    pub value: f64,
+    pub unit: String,
+    /// Spread of the value, e.g. libtest's `(+/- N)`
+    pub dispersion: Option<f64>,
+    /// Confidence interval of the value
+    pub interval: Option<Interval>,
+}
+
+impl Measurement {
+    pub fn new(metric: &str, value: f64, unit: &str) -> Measurement {
+        Measurement {
+            metric: metric.to_string(),
+            value,
+            unit: unit.to_string(),
+            dispersion: None,
+            interval: None,
+        }
+    }
+
+    pub fn with_dispersion(mut self, dispersion: f64) -> Measurement {
+        self.dispersion = Some(dispersion);
+        self
+    }
+
+    pub fn with_interval(mut self, interval: Interval) -> Measurement {
+        self.interval = Some(interval);
+        self
+    }
+}
+
+/// Whether a benchmark ran successfully.
+///
+/// libtest does not tell benchmarks from tests, so ignored and failed tests
+/// run by `cargo bench` are reported as well.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "status", rename_all = "snake_case"))]
+pub enum BenchOutcome {
+    #[default]
+    Ok,
+    Ignored,
+    /// The benchmark panicked, with its captured output if it was printed
+    Failed {
+        output: Option<String>,
+    },
+}
+
+impl fmt::Display for BenchOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            BenchOutcome::Ok => "ok",
+            BenchOutcome::Ignored => "ignored",
+            BenchOutcome::Failed { .. } => "failed",
+        })
+    }
+}
+
+/// A bench binary, as printed by cargo before running it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BenchSource {
+    /// e.g. benches/foo.rs, not printed by older cargo versions
+    pub src_path: Option<String>,
+    /// Name of the bench target, e.g. foo
+    pub target: String,
+    /// Hash of the binary, e.g. 3f5b1cbd0a8d1a2e
+    pub hash: Option<String>,
+    /// Only known for library and main binary targets, which are named after their crate
+    pub crate_name: Option<String>,
+}
+
+/// A point estimate with its confidence interval.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Interval {
+    pub lower: f64,
+    pub estimate: f64,
+    pub upper: f64,
 }
 
 impl Eq for Benchmark {}
@@ -32,87 +251,716 @@ impl PartialEq for Benchmark {
 
 impl Ord for Benchmark {
     fn cmp(&self, other: &Benchmark) -> cmp::Ordering {
-        self.partial_cmp(other).unwrap()
+        self.name.cmp(&other.name)
     }
 }
 
 impl PartialOrd for Benchmark {
     fn partial_cmp(&self, other: &Benchmark) -> Option<cmp::Ordering> {
-        self.name.partial_cmp(&other.name)
+        Some(self.cmp(other))
     }
 }
 
+/// The numeric field of a benchmark line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Field {
+    /// The `ns/iter` timing
+    Ns,
+    /// The `(+/- N)` variance
+    Variance,
+    /// The `MB/s` throughput
+    Throughput,
+    /// The value of a counter, e.g. iai's instructions
+    Counter,
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Field::Ns => "ns",
+            Field::Variance => "variance",
+            Field::Throughput => "throughput",
+            Field::Counter => "counter",
+        })
+    }
+}
+
+/// Why a line could not be parsed into a [`Benchmark`].
+///
+/// Spans are byte ranges into the parsed line.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ParseError {
+    /// The line is not a benchmark result at all.
+    NotABenchLine,
+    /// The line is a benchmark result, but the `ns/iter` timing is missing or malformed.
+    MalformedTiming { span: Range<usize> },
+    /// The line is a benchmark result, but the `(+/- N)` variance is missing or malformed.
+    MalformedVariance { span: Range<usize> },
+    /// A Criterion.rs `[lower estimate upper]` interval is malformed.
+    MalformedInterval { span: Range<usize> },
+    /// A libtest JSON bench event has missing or invalid fields.
+    InvalidJson { message: String },
+    /// A number is too large for its type.
+    NumberOverflow {
+        field: Field,
+        text: String,
+        span: Range<usize>,
+    },
+    /// A number consists of commas only.
+    InvalidNumber {
+        field: Field,
+        text: String,
+        span: Range<usize>,
+    },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::NotABenchLine => write!(f, "not a benchmark line"),
+            ParseError::MalformedTiming { span } => {
+                write!(f, "malformed ns/iter timing at {:?}", span)
+            }
+            ParseError::MalformedVariance { span } => {
+                write!(f, "malformed variance at {:?}", span)
+            }
+            ParseError::MalformedInterval { span } => {
+                write!(f, "malformed interval at {:?}", span)
+            }
+            ParseError::InvalidJson { message } => {
+                write!(f, "invalid bench event: {}", message)
+            }
+            ParseError::NumberOverflow { field, text, span } => {
+                write!(f, "{} `{}` at {:?} overflows", field, text, span)
+            }
+            ParseError::InvalidNumber { field, text, span } => {
+                write!(f, "{} `{}` at {:?} is not a number", field, text, span)
+            }
+        }
+    }
+}
+
+impl error::Error for ParseError {}
+
 fn get_benchmark_regex() -> &'static Regex {
     static INSTANCE: OnceCell<Regex> = OnceCell::new();
     INSTANCE.get_or_init(|| {
+        // Everything after `bench:` is optional, so lines with a broken
+        // timing or variance still match and can be reported as such.
         Regex::new(
             r##"(?x)
-        test\s+(?P<name>\S+)                        # test   mod::test_name
-        \s+...\sbench:\s+(?P<ns>[0-9,]+)\s+ns/iter  # ... bench: 1234 ns/iter
-        \s+\(\+/-\s+(?P<variance>[0-9,]+)\)         # (+/- 4321)
-        (?:\s+=\s+(?P<throughput>[0-9,]+)\sMB/s)?   # =   2314 MB/s
+        test\s+(?P<name>\S+)                                        # test   mod::test_name
+        \s+...\sbench:                                              # ... bench:
+        (?:
+            \s+(?P<ns>[0-9,]+(?:\.[0-9]+)?)\s+ns/iter               # 1234.5 ns/iter
+            (?:\s+\(\+/-\s+(?P<variance>[0-9,]+(?:\.[0-9]+)?)\))?  # (+/- 4321)
+            (?:\s+=\s+(?P<throughput>[0-9,]+)\sMB/s)?                # =   2314 MB/s
+        )?
     "##,
         )
         .unwrap()
     })
 }
 
-impl FromStr for Benchmark {
-    type Err = ();
+/// A libtest benchmark result borrowing its name from the parsed line.
+///
+/// Avoids allocations when ingesting large logs, see [`parse_refs`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BenchmarkRef<'a> {
+    /// e.g. mod::test_name
+    pub name: &'a str,
+    /// e.g. test_name
+    pub shortname: &'a str,
+    /// The benchmarks duration
+    pub ns: f64,
+    /// The benchmarks variance
+    pub variance: f64,
+    /// Throughput of the benchmark if available
+    pub throughput: Option<u64>,
+}
 
-    /// Parses a single benchmark line into a Benchmark.
-    fn from_str(line: &str) -> Result<Benchmark, ()> {
-        let caps = match get_benchmark_regex().captures(line) {
-            None => return Err(()),
-            Some(caps) => caps,
+impl<'a> BenchmarkRef<'a> {
+    /// Parses a single libtest benchmark line.
+    pub fn parse(line: &'a str) -> Result<BenchmarkRef<'a>, ParseError> {
+        Self::parse_with(line, &mut get_benchmark_regex().capture_locations())
+    }
+
+    /// Like [`BenchmarkRef::parse`], reusing `locations` so that parsing does
+    /// not allocate.
+    fn parse_with(
+        line: &'a str,
+        locations: &mut regex::CaptureLocations,
+    ) -> Result<BenchmarkRef<'a>, ParseError> {
+        let regex = get_benchmark_regex();
+        let end = match regex.captures_read(locations, line) {
+            None => return Err(ParseError::NotABenchLine),
+            Some(m) => m.end(),
+        };
+        let group = |name: &str| {
+            let index = regex
+                .capture_names()
+                .position(|group| group == Some(name))?;
+            let (start, end) = locations.get(index)?;
+            Some((&line[start..end], start..end))
         };
-        let ns = match parse_commas(&caps["ns"]) {
-            None => return Err(()),
-            Some(ns) => ns,
+        let rest = || trailing_span(line, end);
+        let ns = match group("ns") {
+            None => return Err(ParseError::MalformedTiming { span: rest() }),
+            Some((text, span)) => parse_decimal_field(Field::Ns, text, span)?,
         };
-        let variance = match parse_commas(&caps["variance"]) {
-            None => return Err(()),
-            Some(variance) => variance,
+        let variance = match group("variance") {
+            None => return Err(ParseError::MalformedVariance { span: rest() }),
+            Some((text, span)) => parse_decimal_field(Field::Variance, text, span)?,
         };
-        let throughput = caps
-            .name("throughput")
-            .and_then(|m| parse_commas(m.as_str()));
-        let name = caps["name"].to_string();
-        let shortname = (&name)
-            .rsplit_once(':')
-            .map(|el| el.1)
-            .unwrap_or(&name)
-            .to_string();
-        Ok(Benchmark {
+        let throughput = group("throughput")
+            .map(|(text, span)| parse_int_field(Field::Throughput, text, span))
+            .transpose()?;
+        let name = group("name").unwrap().0;
+        Ok(BenchmarkRef {
             name,
-            shortname,
+            shortname: shortname(name),
             ns,
             variance,
             throughput,
         })
     }
+
+    pub fn to_owned(&self) -> Benchmark {
+        Benchmark::from_libtest(
+            self.name.to_string(),
+            self.ns,
+            self.variance,
+            self.throughput,
+        )
+    }
+}
+
+/// Parses all libtest benchmark lines of `text`, skipping other lines.
+///
+/// Unlike [`Parser`], only understands libtest's pretty output.
+///
+/// Does not allocate per line.
+pub fn parse_refs(text: &str) -> impl Iterator<Item = BenchmarkRef<'_>> {
+    let mut locations = get_benchmark_regex().capture_locations();
+    text.lines()
+        .filter_map(move |line| BenchmarkRef::parse_with(line, &mut locations).ok())
+}
+
+impl FromStr for Benchmark {
+    type Err = ParseError;
+
+    /// Parses a single benchmark line into a Benchmark.
+    fn from_str(line: &str) -> Result<Benchmark, ParseError> {
+        BenchmarkRef::parse(line).map(|bench| bench.to_owned())
+    }
+}
+
+/// Parses a captured number, reporting failures against `field`.
+pub(crate) fn parse_field(field: Field, m: regex::Match) -> Result<u64, ParseError> {
+    parse_int_field(field, m.as_str(), m.range())
+}
+
+/// Parses the number `text` found at `span`, reporting failures against `field`.
+fn parse_int_field(field: Field, text: &str, span: Range<usize>) -> Result<u64, ParseError> {
+    with_commas_dropped(text, str::parse::<u64>).map_err(|err| {
+        let text = text.to_string();
+        match err.kind() {
+            IntErrorKind::PosOverflow => ParseError::NumberOverflow { field, text, span },
+            _ => ParseError::InvalidNumber { field, text, span },
+        }
+    })
+}
+
+/// Parses the decimal number `text` found at `span`, reporting failures
+/// against `field`.
+fn parse_decimal_field(field: Field, text: &str, span: Range<usize>) -> Result<f64, ParseError> {
+    match with_commas_dropped(text, str::parse::<f64>) {
+        Ok(value) if value.is_finite() => Ok(value),
+        Ok(_) => Err(ParseError::NumberOverflow {
+            field,
+            text: text.to_string(),
+            span,
+        }),
+        Err(_) => Err(ParseError::InvalidNumber {
+            field,
+            text: text.to_string(),
+            span,
+        }),
+    }
+}
+
+/// The last segment of a benchmark name, e.g. test_name for mod::test_name
+fn shortname(name: &str) -> &str {
+    name.rsplit_once(':').map(|el| el.1).unwrap_or(name)
+}
+
+/// Span of the non-whitespace text following `from`.
+fn trailing_span(line: &str, from: usize) -> Range<usize> {
+    let tail = &line[from..];
+    let start = from + tail.len() - tail.trim_start().len();
+    start..line.trim_end().len().max(start)
+}
+
+/// Calls `f` with `s` without its commas. Numbers of up to 64 bytes are
+/// copied to the stack instead of allocating.
+fn with_commas_dropped<R>(s: &str, f: impl FnOnce(&str) -> R) -> R {
+    let mut buf = [0u8; 64];
+    if !s.contains(',') || s.len() > buf.len() {
+        return f(&drop_commas(s));
+    }
+    let mut len = 0;
+    for byte in s.bytes().filter(|&byte| byte != b',') {
+        buf[len] = byte;
+        len += 1;
+    }
+    // Only ASCII commas were removed, so the rest is still valid UTF-8
+    f(std::str::from_utf8(&buf[..len]).unwrap())
+}
+
+/// Drops all commas in a string, only allocating if there are any
+fn drop_commas(s: &str) -> Cow<'_, str> {
+    if s.contains(',') {
+        Cow::Owned(s.chars().filter(|&b| b != ',').collect())
+    } else {
+        Cow::Borrowed(s)
+    }
 }
 
-/// Drops all commas in a string and parses it as a unsigned integer
-fn parse_commas(s: &str) -> Option<u64> {
-    drop_commas(s).parse().ok()
+/// Incremental parser for `cargo bench` output, fed one line at a time.
+///
+/// Understands libtest output, in both the pretty and the JSON format,
+/// Criterion.rs output, divan tables and iai output. Criterion.rs, divan and
+/// iai benchmarks span several lines, so a benchmark may only become available after later
+/// lines or [`Parser::finish`].
+#[derive(Debug, Default)]
+pub struct Parser {
+    /// Last unrecognised, unindented line; a Criterion.rs or iai id if
+    /// `time:` or counters follow
+    last_line: Option<String>,
+    /// The bench binary currently running
+    source: Option<BenchSource>,
+    /// Names of the current divan tree nodes, `None` outside of a divan table
+    divan_path: Option<Vec<String>>,
+    /// Benchmark that may still receive a throughput on the following lines
+    pending: Option<Benchmark>,
+    /// Failed benchmarks, which may still receive their output
+    failed: Vec<Benchmark>,
+    /// Name and output of a failed benchmark being captured
+    failure_output: Option<(String, String)>,
+    ready: VecDeque<Event>,
 }
 
-/// Drops all commas in a string
-fn drop_commas(s: &str) -> String {
-    s.chars().filter(|&b| b != ',').collect()
+/// Something found in `cargo bench` output.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum Event {
+    /// cargo started a bench binary
+    Running(BenchSource),
+    /// libtest's `running N tests`
+    Started {
+        test_count: usize,
+    },
+    Benchmark(Benchmark),
+    /// libtest's `test result:` line
+    Summary(TestSummary),
+}
+
+impl Parser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feeds a single line, without line terminator.
+    ///
+    /// Returns an error if the line is not part of a benchmark.
+    pub fn push_line(&mut self, line: &str) -> Result<(), ParseError> {
+        let last_line = self.last_line.take();
+        let libtest_line = libtest::parse_line(line);
+        if self.failure_output.is_some() {
+            match libtest_line {
+                Some(libtest::Line::FailureOutput { .. })
+                | Some(libtest::Line::Failures)
+                | Some(libtest::Line::Summary(_)) => self.store_failure_output(),
+                _ => {
+                    let (_, output) = self.failure_output.as_mut().unwrap();
+                    output.push_str(line);
+                    output.push('\n');
+                    return Ok(());
+                }
+            }
+        }
+        if let Some(source) = cargo::parse_running_line(line) {
+            self.flush();
+            self.flush_failed();
+            self.divan_path = None;
+            self.ready.push_back(Event::Running(source.clone()));
+            self.source = Some(source);
+            return Ok(());
+        }
+        if let Some(line) = libtest_line {
+            self.flush();
+            self.push_libtest_line(line);
+            return Ok(());
+        }
+        if let Some(result) = self.push_divan_line(line) {
+            return result;
+        }
+        if let Some(result) = self.push_criterion_line(line, &last_line) {
+            return result;
+        }
+        if let Some(result) = self.push_iai_line(line, last_line) {
+            return result;
+        }
+        self.flush();
+        let parsed =
+            libtest_json::parse_line(line).unwrap_or_else(|| line.parse().map(Event::Benchmark));
+        match parsed {
+            Ok(Event::Benchmark(bench)) => {
+                self.complete(bench);
+                Ok(())
+            }
+            Ok(Event::Summary(summary)) => {
+                self.flush_failed();
+                self.ready.push_back(Event::Summary(summary));
+                Ok(())
+            }
+            Ok(event) => {
+                self.ready.push_back(event);
+                Ok(())
+            }
+            Err(err) => {
+                if !line.starts_with(char::is_whitespace) && !line.trim().is_empty() {
+                    self.last_line = Some(line.trim_end().to_string());
+                }
+                Err(err)
+            }
+        }
+    }
+
+    fn push_libtest_line(&mut self, line: libtest::Line) {
+        match line {
+            libtest::Line::Started { test_count } => {
+                self.ready.push_back(Event::Started { test_count })
+            }
+            libtest::Line::Outcome {
+                name,
+                outcome: outcome @ BenchOutcome::Failed { .. },
+            } => self.failed.push(Benchmark {
+                outcome,
+                ..Benchmark::from_name(name.to_string())
+            }),
+            libtest::Line::Outcome { name, outcome } => self.complete(Benchmark {
+                outcome,
+                ..Benchmark::from_name(name.to_string())
+            }),
+            libtest::Line::FailureOutput { name } => {
+                self.failure_output = Some((name.to_string(), String::new()))
+            }
+            libtest::Line::Failures => {}
+            libtest::Line::Summary(summary) => {
+                self.flush_failed();
+                self.ready.push_back(Event::Summary(summary));
+            }
+        }
+    }
+
+    /// Attaches the captured output to its failed benchmark.
+    fn store_failure_output(&mut self) {
+        let (name, output) = match self.failure_output.take() {
+            Some(failure_output) => failure_output,
+            None => return,
+        };
+        if let Some(bench) = self.failed.iter_mut().find(|bench| bench.name == name) {
+            bench.outcome = BenchOutcome::Failed {
+                output: Some(output.trim_end().to_string()),
+            };
+        }
+    }
+
+    fn push_divan_line(&mut self, line: &str) -> Option<Result<(), ParseError>> {
+        let parsed = divan::parse_line(line);
+        if let Some(Ok(divan::Line::Header)) = parsed {
+            self.flush();
+            self.divan_path = Some(Vec::new());
+            return Some(Ok(()));
+        }
+        let (path, parsed) = match (&mut self.divan_path, parsed) {
+            (Some(path), Some(parsed)) => (path, parsed),
+            _ => {
+                self.divan_path = None;
+                return None;
+            }
+        };
+        Some(match parsed {
+            Ok(divan::Line::Row {
+                depth,
+                name,
+                timings,
+            }) => {
+                path.truncate(depth);
+                path.push(name.to_string());
+                let bench = timings.map(|timings| {
+                    let time = Measurement::new("time", timings.median, "ns")
+                        .with_dispersion(timings.slowest - timings.fastest);
+                    let mut bench = Benchmark::from_time(path.join("::"), time);
+                    bench.measurements.extend([
+                        Measurement::new("fastest", timings.fastest, "ns"),
+                        Measurement::new("slowest", timings.slowest, "ns"),
+                        Measurement::new("mean", timings.mean, "ns"),
+                    ]);
+                    bench
+                });
+                self.flush();
+                self.pending = bench;
+                Ok(())
+            }
+            Ok(divan::Line::Counter(throughput)) => {
+                if let (Some(bench), Some(throughput)) = (&mut self.pending, throughput) {
+                    bench.push_throughput(throughput);
+                }
+                Ok(())
+            }
+            Ok(divan::Line::Header) => unreachable!(),
+            Err(err) => {
+                self.flush();
+                Err(err)
+            }
+        })
+    }
+
+    fn push_criterion_line(
+        &mut self,
+        line: &str,
+        last_line: &Option<String>,
+    ) -> Option<Result<(), ParseError>> {
+        Some(match criterion::parse_line(line)? {
+            Ok(criterion::Line::Time { id, interval }) => {
+                self.flush();
+                let name = match (id, last_line) {
+                    ("", Some(last_line)) => last_line.clone(),
+                    ("", None) => return Some(Err(ParseError::NotABenchLine)),
+                    (id, _) => id.to_string(),
+                };
+                let time = Measurement::new("time", interval.estimate, "ns")
+                    .with_dispersion(interval.upper - interval.lower)
+                    .with_interval(interval);
+                self.pending = Some(Benchmark::from_time(name, time));
+                Ok(())
+            }
+            Ok(line) => match &mut self.pending {
+                Some(bench) => {
+                    if let criterion::Line::Throughput(throughput) = line {
+                        bench.push_throughput(throughput);
+                    }
+                    Ok(())
+                }
+                None => Err(ParseError::NotABenchLine),
+            },
+            Err(err) => {
+                self.flush();
+                Err(err)
+            }
+        })
+    }
+
+    fn push_iai_line(
+        &mut self,
+        line: &str,
+        last_line: Option<String>,
+    ) -> Option<Result<(), ParseError>> {
+        let counter = match iai::parse_line(line)? {
+            Ok(counter) => counter,
+            Err(err) => {
+                self.flush();
+                return Some(Err(err));
+            }
+        };
+        match &mut self.pending {
+            Some(bench) if bench.measurement("Instructions").is_some() => {
+                bench.measurements.push(counter)
+            }
+            // Every benchmark starts with its instruction count
+            _ if counter.metric == "Instructions" => {
+                self.flush();
+                self.pending = Some(Benchmark {
+                    measurements: vec![counter],
+                    ..Benchmark::from_name(last_line?)
+                });
+            }
+            _ => return None,
+        }
+        Some(Ok(()))
+    }
+
+    /// Marks the end of the input, completing any pending benchmark.
+    pub fn finish(&mut self) {
+        self.store_failure_output();
+        self.flush();
+        self.flush_failed();
+        self.last_line = None;
+        self.source = None;
+        self.divan_path = None;
+    }
+
+    /// Removes and returns all completed benchmarks, dropping other events.
+    pub fn drain(&mut self) -> impl Iterator<Item = Benchmark> + '_ {
+        self.ready.drain(..).filter_map(|event| match event {
+            Event::Benchmark(bench) => Some(bench),
+            _ => None,
+        })
+    }
+
+    /// Removes and returns the oldest event.
+    pub fn next_event(&mut self) -> Option<Event> {
+        self.ready.pop_front()
+    }
+
+    /// Removes and returns all events, including completed benchmarks.
+    pub fn drain_events(&mut self) -> impl Iterator<Item = Event> + '_ {
+        self.ready.drain(..)
+    }
+
+    fn flush(&mut self) {
+        if let Some(bench) = self.pending.take() {
+            self.complete(bench);
+        }
+    }
+
+    fn flush_failed(&mut self) {
+        for bench in std::mem::take(&mut self.failed) {
+            self.complete(bench);
+        }
+    }
+
+    fn complete(&mut self, mut bench: Benchmark) {
+        bench.source = self.source.clone();
+        self.ready.push_back(Event::Benchmark(bench));
+    }
 }
 
 /// Parse benchmarks from a buffered reader.
+///
+/// Use [`BenchIter`] to process benchmarks as they are parsed.
 pub fn parse_lines<B: BufRead>(buffer: B) -> io::Result<Vec<Benchmark>> {
-    let iter = buffer.lines();
-    let mut vec = Vec::with_capacity(iter.size_hint().0);
-    for result in iter {
-        if let Ok(bench) = result?.parse() {
-            vec.push(bench)
+    BenchIter::new(buffer).collect()
+}
+
+/// A line that could not be parsed into a [`Benchmark`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SkippedLine {
+    /// 1-based line number
+    pub line_number: usize,
+    /// The line itself, without line terminator
+    pub line: String,
+    /// Why the line was skipped
+    pub reason: ParseError,
+}
+
+impl SkippedLine {
+    /// Whether the line looks like a benchmark result (`test ` ... `bench:`,
+    /// or a JSON bench event), i.e. a benchmark was probably lost rather than
+    /// unrelated output skipped.
+    pub fn is_bench_line(&self) -> bool {
+        (self.line.trim_start().starts_with("test ") && self.line.contains("bench:"))
+            || matches!(self.reason, ParseError::InvalidJson { .. })
+    }
+}
+
+impl fmt::Display for SkippedLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}: {}: {}",
+            self.line_number, self.reason, self.line
+        )
+    }
+}
+
+/// Benchmarks parsed from a reader, together with all lines that were skipped.
+#[derive(Clone, Debug, Default)]
+pub struct ParseReport {
+    pub benchmarks: Vec<Benchmark>,
+    pub skipped: Vec<SkippedLine>,
+}
+
+impl ParseReport {
+    /// Skipped lines that look like benchmark results.
+    pub fn skipped_bench_lines(&self) -> impl Iterator<Item = &SkippedLine> {
+        self.skipped
+            .iter()
+            .filter(|skipped| skipped.is_bench_line())
+    }
+
+    /// Returns the benchmarks, or the first skipped line that looks like a
+    /// benchmark result.
+    pub fn into_strict(self) -> Result<Vec<Benchmark>, SkippedLine> {
+        match self.skipped.into_iter().find(SkippedLine::is_bench_line) {
+            Some(skipped) => Err(skipped),
+            None => Ok(self.benchmarks),
+        }
+    }
+}
+
+/// Parse benchmarks from a buffered reader, keeping track of skipped lines.
+pub fn parse_lines_report<B: BufRead>(buffer: B) -> io::Result<ParseReport> {
+    let mut report = ParseReport::default();
+    let mut parser = Parser::new();
+    for (index, result) in buffer.lines().enumerate() {
+        let line = result?;
+        if let Err(reason) = parser.push_line(&line) {
+            report.skipped.push(SkippedLine {
+                line_number: index + 1,
+                line,
+                reason,
+            });
+        }
+        report.benchmarks.extend(parser.drain());
+    }
+    parser.finish();
+    report.benchmarks.extend(parser.drain());
+    Ok(report)
+}
+
+/// Error of [`parse_lines_strict`].
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    /// A line looking like a benchmark result could not be parsed.
+    Parse(SkippedLine),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => err.fmt(f),
+            Error::Parse(skipped) => skipped.fmt(f),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            Error::Parse(skipped) => Some(&skipped.reason),
         }
     }
-    Ok(vec)
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+/// Parse benchmarks from a buffered reader, failing on the first line that
+/// looks like a benchmark result but cannot be parsed.
+pub fn parse_lines_strict<B: BufRead>(buffer: B) -> Result<Vec<Benchmark>, Error> {
+    parse_lines_report(buffer)?
+        .into_strict()
+        .map_err(Error::Parse)
 }
 
 #[cfg(test)]
@@ -142,6 +990,295 @@ test fastfield::multivalued::bench::bench_multi_value_fflookup
         );
 
         let shortnames: Vec<_> = benchmarks.iter().map(|bench| bench.ns).collect();
-        assert_eq!(shortnames, &[95653541, 103466980, 1330510]);
+        assert_eq!(shortnames, &[95653541.0, 103466980.0, 1330510.0]);
+
+        assert_eq!(
+            benchmarks[0].duration(),
+            Some(Duration::from_nanos(95653541))
+        );
+        let huge: Benchmark = "test a ... bench: 100000000000000000000000000000 ns/iter (+/- 1)"
+            .parse()
+            .unwrap();
+        assert_eq!(huge.duration(), None);
+        assert_eq!(Benchmark::from_name("a".to_string()).duration(), None);
+        let mut ignored = benchmarks[0].clone();
+        ignored.outcome = BenchOutcome::Ignored;
+        assert_eq!(ignored.duration(), None);
+        let relative_variance = benchmarks[0].relative_variance().unwrap();
+        assert_eq!(format!("{:.2}", relative_variance), "1.47");
+        assert_eq!(
+            Benchmark::from_name("a".to_string()).relative_variance(),
+            None
+        );
+    }
+
+    #[test]
+    fn parse_error_test() {
+        assert_eq!(
+            "running 3 tests".parse::<Benchmark>().unwrap_err(),
+            ParseError::NotABenchLine
+        );
+
+        let line = "test a::b ... bench: 1 ns/iter (+/- 1) = 99,999,999,999,999,999,999 MB/s";
+        assert_eq!(
+            line.parse::<Benchmark>().unwrap_err(),
+            ParseError::NumberOverflow {
+                field: Field::Throughput,
+                text: "99,999,999,999,999,999,999".to_string(),
+                span: 41..67,
+            }
+        );
+
+        let line = "test a::b ... bench: 1,234 ns/iter (+/- ???) ";
+        assert_eq!(
+            line.parse::<Benchmark>().unwrap_err(),
+            ParseError::MalformedVariance { span: 35..44 }
+        );
+        assert_eq!(&line[35..44], "(+/- ???)");
+
+        let line = "test a::b ... bench: fast";
+        assert_eq!(
+            line.parse::<Benchmark>().unwrap_err(),
+            ParseError::MalformedTiming { span: 21..25 }
+        );
+    }
+
+    #[test]
+    fn parse_report_test() {
+        let data = format!(
+            "{}\nCompiling tantivy\ntest a::b ... bench: 1.5 ms/iter (+/- 0)",
+            TEST_DATA
+        );
+        let report = parse_lines_report(BufReader::new(data.as_bytes())).unwrap();
+        assert_eq!(report.benchmarks.len(), 3);
+        assert_eq!(report.skipped.len(), 2);
+        assert!(!report.skipped[0].is_bench_line());
+
+        let skipped: Vec<_> = report.skipped_bench_lines().collect();
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].line_number, 6);
+        assert!(matches!(
+            skipped[0].reason,
+            ParseError::MalformedTiming { .. }
+        ));
+
+        assert!(parse_lines_strict(BufReader::new(TEST_DATA.as_bytes())).is_ok());
+        let err = parse_lines_strict(BufReader::new(data.as_bytes())).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Parse(SkippedLine { line_number: 6, .. })
+        ));
+    }
+
+    const CRITERION_DATA: &str = r#"Benchmarking fib 20: Analyzing
+fib 20                  time:   [26.029 µs 26.251 µs 26.505 µs]
+                        change: [-1.2% +0.3% +1.8%] (p = 0.71 > 0.05)
+                        No change in performance detected.
+Found 11 outliers among 100 measurements (11.00%)
+  6 (6.00%) high mild
+
+Benchmarking parse/very_long_parameter/1024: Analyzing
+parse/very_long_parameter/1024
+                        time:   [1.0000 ms 1.2500 ms 1.5000 ms]
+                        thrpt:  [666.67 MB/s 800.00 MB/s 1.0000 GB/s]
+                 change:
+                        time:   [-1.2% +0.3% +1.8%] (p = 0.71 > 0.05)
+                        thrpt:  [-1.8% -0.3% +1.2%]"#;
+
+    #[test]
+    fn parse_criterion_output_test() {
+        let reader = BufReader::new(CRITERION_DATA.as_bytes());
+        let benchmarks = parse_lines(reader).unwrap();
+
+        let names: Vec<_> = benchmarks.iter().map(|bench| &bench.name).collect();
+        assert_eq!(names, &["fib 20", "parse/very_long_parameter/1024"]);
+        assert_eq!(benchmarks[0].ns, 26251.0);
+        assert_eq!(benchmarks[0].variance.round(), 476.0);
+        assert_eq!(benchmarks[0].throughput, None);
+        assert_eq!(
+            benchmarks[1].interval(),
+            Some(Interval {
+                lower: 1_000_000.0,
+                estimate: 1_250_000.0,
+                upper: 1_500_000.0
+            })
+        );
+        assert_eq!(benchmarks[1].throughput, Some(800));
+    }
+
+    #[test]
+    fn parse_mixed_json_output_test() {
+        let data = format!(
+            "{}\n{}\n{}",
+            r#"{ "type": "suite", "event": "started", "test_count": 2 }"#,
+            r#"{ "type": "bench", "name": "a::json", "median": 1234, "deviation": 56 }"#,
+            "test a::pretty ... bench:   1,000 ns/iter (+/- 10)",
+        );
+        let benchmarks = parse_lines(BufReader::new(data.as_bytes())).unwrap();
+        let names: Vec<_> = benchmarks.iter().map(|bench| &bench.name).collect();
+        assert_eq!(names, &["a::json", "a::pretty"]);
+    }
+
+    const DIVAN_DATA: &str = r#"     Running benches/example.rs (target/release/deps/example-3f5b1cbd0a8d1a2e)
+Timer precision: 41 ns
+example             fastest       │ slowest       │ median        │ mean          │ samples │ iters
+├─ fibonacci                      │               │               │               │         │
+│  ├─ 0             0.159 ns      │ 0.172 ns      │ 0.162 ns      │ 0.162 ns      │ 100     │ 3276800
+│  ╰─ 5             6.834 ns      │ 7.568 ns      │ 6.893 ns      │ 6.928 ns      │ 100     │ 102400
+╰─ copy             227.6 ns      │ 294.7 ns      │ 236.6 ns      │ 240.9 ns      │ 100     │ 1600
+                    4.497 GB/s    │ 3.474 GB/s    │ 4.327 GB/s    │ 4.25 GB/s     │         │
+
+"#;
+
+    #[test]
+    fn parse_divan_output_test() {
+        let reader = BufReader::new(DIVAN_DATA.as_bytes());
+        let benchmarks = parse_lines(reader).unwrap();
+
+        let names: Vec<_> = benchmarks.iter().map(|bench| &bench.name).collect();
+        assert_eq!(names, &["fibonacci::0", "fibonacci::5", "copy"]);
+        let targets: Vec<_> = benchmarks
+            .iter()
+            .map(|bench| bench.source.as_ref().unwrap().target.as_str())
+            .collect();
+        assert_eq!(targets, &["example", "example", "example"]);
+        let ns: Vec<_> = benchmarks.iter().map(|bench| bench.ns).collect();
+        assert_eq!(ns, &[0.162, 6.893, 236.6]);
+        assert_eq!(benchmarks[2].variance.round(), 67.0);
+        assert_eq!(benchmarks[2].throughput, Some(4327));
+        assert_eq!(benchmarks[2].measurement("mean").unwrap().value, 240.9);
+        assert_eq!(benchmarks[1].throughput, None);
+    }
+
+    const IAI_DATA: &str = r#"     Running benches/iai.rs (target/release/deps/iai-3f5b1cbd0a8d1a2e)
+iai::bench_fibonacci_short
+  Instructions:                1735|1735            (No change)
+  L1 Hits:                     2359|2359            (No change)
+  Estimated Cycles:            2464|2464            (No change)
+bench_fibonacci_long
+  Instructions:            26214735 (+0.000011%)
+  L1 Accesses:             35638623 (+0.000011%)"#;
+
+    #[test]
+    fn parse_iai_output_test() {
+        let reader = BufReader::new(IAI_DATA.as_bytes());
+        let benchmarks = parse_lines(reader).unwrap();
+
+        let shortnames: Vec<_> = benchmarks.iter().map(|bench| &bench.shortname).collect();
+        assert_eq!(
+            shortnames,
+            &["bench_fibonacci_short", "bench_fibonacci_long"]
+        );
+        assert_eq!(benchmarks[0].measurements.len(), 3);
+        assert_eq!(
+            benchmarks[1].measurements[1],
+            Measurement::new("L1 Accesses", 35638623.0, "")
+        );
+        assert_eq!(benchmarks[1].measurement("time"), None);
+        assert_eq!(benchmarks[1].ns, 0.0);
+    }
+
+    #[test]
+    fn parse_fractional_test() {
+        let bench: Benchmark = "test a::b ... bench:           0.31 ns/iter (+/- 0.01)"
+            .parse()
+            .unwrap();
+        assert_eq!(bench.ns, 0.31);
+        assert_eq!(bench.variance, 0.01);
+
+        let bench: Benchmark = "test a::b ... bench:   1,234.50 ns/iter (+/- 7)"
+            .parse()
+            .unwrap();
+        assert_eq!(bench.ns, 1234.5);
+        assert_eq!(bench.variance, 7.0);
+    }
+
+    const FAILED_DATA: &str = r#"running 3 tests
+test a::ignored ... ignored
+test a::panics ... FAILED
+test a::works  ... bench:         100 ns/iter (+/- 3)
+
+failures:
+
+---- a::panics stdout ----
+thread 'main' panicked at 'boom', src/lib.rs:10:5
+note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace
+
+
+failures:
+    a::panics
+
+test result: FAILED. 0 passed; 1 failed; 1 ignored; 1 measured; 0 filtered out; finished in 0.10s
+"#;
+
+    #[test]
+    fn parse_outcomes_test() {
+        let reader = BufReader::new(FAILED_DATA.as_bytes());
+        let benchmarks = parse_lines(reader).unwrap();
+
+        let outcomes: Vec<_> = benchmarks
+            .iter()
+            .map(|bench| (bench.name.as_str(), bench.outcome.to_string()))
+            .collect();
+        assert_eq!(
+            outcomes,
+            &[
+                ("a::ignored", "ignored".to_string()),
+                ("a::works", "ok".to_string()),
+                ("a::panics", "failed".to_string())
+            ]
+        );
+        assert_eq!(
+            benchmarks[2].outcome,
+            BenchOutcome::Failed {
+                output: Some(
+                    "thread 'main' panicked at 'boom', src/lib.rs:10:5\n\
+                     note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace"
+                        .to_string()
+                )
+            }
+        );
+    }
+
+    #[test]
+    fn parse_refs_test() {
+        let benchmarks: Vec<_> = parse_refs(TEST_DATA).collect();
+        assert_eq!(benchmarks.len(), 3);
+        assert_eq!(
+            benchmarks[2],
+            BenchmarkRef {
+                name: "fastfield::multivalued::bench::bench_multi_value_fflookup",
+                shortname: "bench_multi_value_fflookup",
+                ns: 1330510.0,
+                variance: 217966.0,
+                throughput: None,
+            }
+        );
+
+        let bench = benchmarks[2].to_owned();
+        assert_eq!(bench.name, benchmarks[2].name);
+        assert_eq!(bench.shortname, benchmarks[2].shortname);
+        assert_eq!(bench.ns, benchmarks[2].ns);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_test() {
+        let benchmarks = parse_lines(BufReader::new(FAILED_DATA.as_bytes())).unwrap();
+        let json = serde_json::to_string(&benchmarks).unwrap();
+        let deserialized: Vec<Benchmark> = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized.len(), 3);
+        assert_eq!(deserialized[1].measurements, benchmarks[1].measurements);
+        assert_eq!(deserialized[2].outcome, benchmarks[2].outcome);
+
+        let value: serde_json::Value = serde_json::to_value(&benchmarks[2]).unwrap();
+        assert_eq!(value["outcome"]["status"], "failed");
+
+        let minimal: Benchmark = serde_json::from_str(
+            r#"{"name": "a::b", "shortname": "b", "ns": 1.5, "variance": 0.5}"#,
+        )
+        .unwrap();
+        assert_eq!(minimal.outcome, BenchOutcome::Ok);
+        assert_eq!(minimal.throughput, None);
     }
 }
@@ -0,0 +1,153 @@
+//! Parsing of the libtest lines around the benchmark results.
+//!
+//! ```text
+//! running 2 tests
+//! test mod::bench_foo ... ignored
+//! test mod::bench_bar ... FAILED
+//!
+//! failures:
+//!
+//! ---- mod::bench_bar stdout ----
+//! thread 'main' panicked at 'boom', src/lib.rs:10:5
+//!
+//! failures:
+//!     mod::bench_bar
+//!
+//! test result: FAILED. 0 passed; 1 failed; 1 ignored; 0 measured; 0 filtered out
+//! ```
+
+use once_cell::sync::OnceCell;
+use regex::Regex;
+
+use crate::{BenchOutcome, TestSummary};
+
+/// A libtest line that is not a benchmark result.
+#[derive(Debug, PartialEq)]
+pub(crate) enum Line<'a> {
+    /// `running N tests`
+    Started { test_count: usize },
+    /// `test name ... ignored` or `test name ... FAILED`
+    Outcome {
+        name: &'a str,
+        outcome: BenchOutcome,
+    },
+    /// `---- name stdout ----`, followed by the captured output of a failure
+    FailureOutput { name: &'a str },
+    /// `failures:`, starting and ending the captured outputs
+    Failures,
+    /// `test result: ...`
+    Summary(TestSummary),
+}
+
+fn get_started_regex() -> &'static Regex {
+    static INSTANCE: OnceCell<Regex> = OnceCell::new();
+    INSTANCE.get_or_init(|| Regex::new(r"^running (?P<test_count>\d+) tests?$").unwrap())
+}
+
+fn get_outcome_regex() -> &'static Regex {
+    static INSTANCE: OnceCell<Regex> = OnceCell::new();
+    INSTANCE.get_or_init(|| {
+        Regex::new(r"^test\s+(?P<name>\S+)\s+\.\.\.\s+(?P<outcome>ignored|FAILED)\b").unwrap()
+    })
+}
+
+fn get_failure_output_regex() -> &'static Regex {
+    static INSTANCE: OnceCell<Regex> = OnceCell::new();
+    INSTANCE.get_or_init(|| Regex::new(r"^---- (?P<name>\S+) std(?:out|err) ----$").unwrap())
+}
+
+fn get_summary_regex() -> &'static Regex {
+    static INSTANCE: OnceCell<Regex> = OnceCell::new();
+    INSTANCE.get_or_init(|| {
+        Regex::new(
+            r##"(?x)
+        ^test\ result:\ (?P<result>ok|FAILED)\.       # test result: ok.
+        \ (?P<passed>\d+)\ passed;                    # 0 passed;
+        \ (?P<failed>\d+)\ failed;                    # 0 failed;
+        \ (?P<ignored>\d+)\ ignored;                  # 0 ignored;
+        \ (?P<measured>\d+)\ measured;                # 3 measured;
+        \ (?P<filtered_out>\d+)\ filtered\ out        # 0 filtered out
+    "##,
+        )
+        .unwrap()
+    })
+}
+
+/// Parses a libtest line, `None` if it is not one of [`Line`].
+pub(crate) fn parse_line(line: &str) -> Option<Line<'_>> {
+    let line = line.trim_end();
+    if let Some(caps) = get_started_regex().captures(line) {
+        let test_count = caps["test_count"].parse().ok()?;
+        return Some(Line::Started { test_count });
+    }
+    if let Some(caps) = get_outcome_regex().captures(line) {
+        let outcome = match &caps["outcome"] {
+            "ignored" => BenchOutcome::Ignored,
+            _ => BenchOutcome::Failed { output: None },
+        };
+        let name = caps.name("name").unwrap().as_str();
+        return Some(Line::Outcome { name, outcome });
+    }
+    if let Some(caps) = get_failure_output_regex().captures(line) {
+        let name = caps.name("name").unwrap().as_str();
+        return Some(Line::FailureOutput { name });
+    }
+    if line == "failures:" {
+        return Some(Line::Failures);
+    }
+    if let Some(caps) = get_summary_regex().captures(line) {
+        let count = |name: &str| caps[name].parse().ok();
+        return Some(Line::Summary(TestSummary {
+            ok: &caps["result"] == "ok",
+            passed: count("passed")?,
+            failed: count("failed")?,
+            ignored: count("ignored")?,
+            measured: count("measured")?,
+            filtered_out: count("filtered_out")?,
+        }));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_libtest_line_test() {
+        assert_eq!(
+            parse_line("test a::b ... ignored, too slow"),
+            Some(Line::Outcome {
+                name: "a::b",
+                outcome: BenchOutcome::Ignored
+            })
+        );
+        assert_eq!(
+            parse_line("test a::b ... FAILED"),
+            Some(Line::Outcome {
+                name: "a::b",
+                outcome: BenchOutcome::Failed { output: None }
+            })
+        );
+        assert_eq!(
+            parse_line("---- a::b stdout ----"),
+            Some(Line::FailureOutput { name: "a::b" })
+        );
+        assert_eq!(
+            parse_line("running 1 test"),
+            Some(Line::Started { test_count: 1 })
+        );
+        assert_eq!(
+            parse_line("test result: FAILED. 1 passed; 2 failed; 3 ignored; 4 measured; 5 filtered out; finished in 0.10s"),
+            Some(Line::Summary(TestSummary {
+                ok: false,
+                passed: 1,
+                failed: 2,
+                ignored: 3,
+                measured: 4,
+                filtered_out: 5
+            }))
+        );
+        assert_eq!(parse_line("test a::b ... ok"), None);
+    }
+}
@@ -0,0 +1,187 @@
+//! Parsing of libtest's JSON output (`-Z unstable-options --format json`).
+//!
+//! ```text
+//! { "type": "bench", "name": "mod::bench_foo", "median": 1234, "deviation": 56, "mib_per_second": 78 }
+//! { "type": "test", "event": "failed", "name": "mod::bench_bar", "stdout": "thread 'main' panicked ..." }
+//! { "type": "suite", "event": "failed", "passed": 0, "failed": 1, "ignored": 0, "measured": 1, "filtered_out": 0 }
+//! ```
+
+use serde::Deserialize;
+
+use crate::{BenchOutcome, Benchmark, Event, ParseError, TestSummary};
+
+#[derive(Deserialize)]
+struct BenchEvent {
+    name: String,
+    median: f64,
+    deviation: f64,
+    mib_per_second: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct SuiteEvent {
+    event: String,
+    test_count: Option<usize>,
+    passed: Option<usize>,
+    failed: Option<usize>,
+    ignored: Option<usize>,
+    measured: Option<usize>,
+    filtered_out: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct TestEvent {
+    name: String,
+    event: String,
+    stdout: Option<String>,
+}
+
+/// Parses a line of libtest JSON output, `None` if it is not a JSON object.
+pub(crate) fn parse_line(line: &str) -> Option<Result<Event, ParseError>> {
+    if !line.trim_start().starts_with('{') {
+        return None;
+    }
+    let value: serde_json::Value = match serde_json::from_str(line) {
+        Ok(value) => value,
+        Err(_) => return Some(Err(ParseError::NotABenchLine)),
+    };
+    match value.get("type").and_then(|ty| ty.as_str()) {
+        Some("bench") => {}
+        Some("test") => return Some(parse_test_event(value).map(Event::Benchmark)),
+        Some("suite") => return Some(parse_suite_event(value)),
+        _ => return Some(Err(ParseError::NotABenchLine)),
+    }
+    let event: BenchEvent = match serde_json::from_value(value) {
+        Ok(event) => event,
+        Err(err) => {
+            return Some(Err(ParseError::InvalidJson {
+                message: err.to_string(),
+            }))
+        }
+    };
+    for (field, value) in [("median", event.median), ("deviation", event.deviation)] {
+        if value < 0.0 {
+            return Some(Err(ParseError::InvalidJson {
+                message: format!("negative {}: {}", field, value),
+            }));
+        }
+    }
+    Some(Ok(Event::Benchmark(Benchmark::from_libtest(
+        event.name,
+        event.median,
+        event.deviation,
+        event.mib_per_second,
+    ))))
+}
+
+/// Parses the `started` event and the final `ok` or `failed` event of a suite.
+fn parse_suite_event(value: serde_json::Value) -> Result<Event, ParseError> {
+    let event: SuiteEvent = match serde_json::from_value(value) {
+        Ok(event) => event,
+        Err(_) => return Err(ParseError::NotABenchLine),
+    };
+    let summary = |ok: bool| -> Option<Event> {
+        Some(Event::Summary(TestSummary {
+            ok,
+            passed: event.passed?,
+            failed: event.failed?,
+            ignored: event.ignored?,
+            measured: event.measured?,
+            filtered_out: event.filtered_out?,
+        }))
+    };
+    let parsed = match event.event.as_str() {
+        "started" => event
+            .test_count
+            .map(|test_count| Event::Started { test_count }),
+        "ok" => summary(true),
+        "failed" => summary(false),
+        _ => None,
+    };
+    parsed.ok_or(ParseError::NotABenchLine)
+}
+
+/// Parses an `ignored` or `failed` event into a benchmark without measurements.
+fn parse_test_event(value: serde_json::Value) -> Result<Benchmark, ParseError> {
+    let event: TestEvent = match serde_json::from_value(value) {
+        Ok(event) => event,
+        Err(_) => return Err(ParseError::NotABenchLine),
+    };
+    let outcome = match event.event.as_str() {
+        "ignored" => BenchOutcome::Ignored,
+        "failed" => BenchOutcome::Failed {
+            output: event.stdout.map(|stdout| stdout.trim_end().to_string()),
+        },
+        _ => return Err(ParseError::NotABenchLine),
+    };
+    Ok(Benchmark {
+        outcome,
+        ..Benchmark::from_name(event.name)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_bench(line: &str) -> Benchmark {
+        match parse_line(line) {
+            Some(Ok(Event::Benchmark(bench))) => bench,
+            other => panic!("not a benchmark: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_json_line_test() {
+        let line = r#"{ "type": "bench", "name": "a::b", "median": 1234, "deviation": 56, "mib_per_second": 78 }"#;
+        let bench = parse_bench(line);
+        assert_eq!(bench.name, "a::b");
+        assert_eq!(bench.shortname, "b");
+        assert_eq!(bench.ns, 1234.0);
+        assert_eq!(bench.variance, 56.0);
+        assert_eq!(bench.throughput, Some(78));
+
+        let line = r#"{ "type": "test", "event": "failed", "name": "a::c", "stdout": "boom\n" }"#;
+        assert_eq!(
+            parse_bench(line).outcome,
+            BenchOutcome::Failed {
+                output: Some("boom".to_string())
+            }
+        );
+
+        let line = r#"{ "type": "test", "event": "started", "name": "a::c" }"#;
+        assert!(matches!(
+            parse_line(line),
+            Some(Err(ParseError::NotABenchLine))
+        ));
+
+        let line = r#"{ "type": "suite", "event": "started", "test_count": 3 }"#;
+        assert!(matches!(
+            parse_line(line),
+            Some(Ok(Event::Started { test_count: 3 }))
+        ));
+
+        let line = r#"{ "type": "suite", "event": "ok", "passed": 0, "failed": 0, "ignored": 0, "measured": 3, "filtered_out": 0, "exec_time": 1.5 }"#;
+        assert!(matches!(
+            parse_line(line),
+            Some(Ok(Event::Summary(TestSummary {
+                ok: true,
+                measured: 3,
+                ..
+            })))
+        ));
+
+        let line = r#"{ "type": "bench", "name": "a::b", "median": -1, "deviation": 56 }"#;
+        assert!(matches!(
+            parse_line(line),
+            Some(Err(ParseError::InvalidJson { .. }))
+        ));
+        let line = r#"{ "type": "bench", "name": "a::b", "median": 1, "deviation": -0.5 }"#;
+        assert!(matches!(
+            parse_line(line),
+            Some(Err(ParseError::InvalidJson { .. }))
+        ));
+
+        assert!(parse_line("running 3 tests").is_none());
+    }
+}
@@ -0,0 +1,552 @@
+//! Flat benchmark rows and the output formats of `cargobench_to_csv`.
+
+use std::{
+    fmt,
+    io::{self, Write},
+    str::FromStr,
+};
+
+use crate::{
+    csv::CsvWriter,
+    human::{group_thousands, relative_percent, HumanDuration},
+    Aggregate, BenchOutcome, Benchmark,
+};
+
+/// Output format of the rows.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+    #[default]
+    Csv,
+    Tsv,
+    /// A single JSON array of row objects
+    Json,
+    /// One JSON row object per line
+    Ndjson,
+    /// A GitHub flavored Markdown table with human-readable units, see
+    /// [`crate::human`]
+    Markdown,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(Format::Csv),
+            "tsv" => Ok(Format::Tsv),
+            "json" => Ok(Format::Json),
+            "ndjson" => Ok(Format::Ndjson),
+            "markdown" | "md" => Ok(Format::Markdown),
+            _ => Err(format!(
+                "unknown format '{}', expected csv, tsv, json, ndjson or markdown",
+                s
+            )),
+        }
+    }
+}
+
+/// One output row, a benchmark's time or one of its counters.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Row {
+    pub name: String,
+    /// The time, or the counter's value; `None` if the benchmark did not run
+    pub value: Option<f64>,
+    /// `ns` for times, the counter's unit otherwise
+    pub unit: String,
+    pub variance: Option<f64>,
+    /// MB/s
+    pub throughput: Option<u64>,
+    pub outcome: BenchOutcome,
+    /// The bench target the benchmark belongs to, if cargo printed it
+    pub target: Option<String>,
+    /// The input file the benchmark was read from
+    pub file: Option<String>,
+}
+
+impl Row {
+    /// The rows of a benchmark: one with its time, or one per measurement for
+    /// benchmarks without a time, e.g. iai counters, named `name/metric`.
+    pub fn from_benchmark(bench: &Benchmark) -> Vec<Row> {
+        let row = |name: String, value, unit: &str| Row {
+            name,
+            value,
+            unit: unit.to_string(),
+            variance: None,
+            throughput: None,
+            outcome: bench.outcome.clone(),
+            target: bench.source.as_ref().map(|source| source.target.clone()),
+            file: None,
+        };
+        if bench.outcome != BenchOutcome::Ok {
+            return vec![row(bench.name.clone(), None, "")];
+        }
+        if bench.measurement("time").is_none() {
+            return bench
+                .measurements
+                .iter()
+                .map(|measurement| {
+                    let name = format!("{}/{}", bench.name, measurement.metric);
+                    row(name, Some(measurement.value), &measurement.unit)
+                })
+                .collect();
+        }
+        vec![Row {
+            variance: Some(bench.variance),
+            throughput: bench.throughput,
+            ..row(bench.name.clone(), Some(bench.ns), "ns")
+        }]
+    }
+
+    /// Tags the row with the input file it was read from.
+    pub fn with_file(mut self, file: &str) -> Self {
+        self.file = Some(file.to_string());
+        self
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "name": self.name,
+            "value": self.value,
+            "unit": self.unit,
+            "variance": self.variance,
+            "throughput": self.throughput,
+            "outcome": self.outcome.to_string(),
+            "target": self.target,
+            "file": self.file,
+        })
+    }
+}
+
+/// Writes rows in one of the [`Format`]s.
+pub struct RowWriter<W> {
+    writer: W,
+    format: Format,
+    header: bool,
+    delimiter: Option<char>,
+}
+
+impl<W: Write> RowWriter<W> {
+    pub fn new(writer: W, format: Format) -> Self {
+        RowWriter {
+            writer,
+            format,
+            header: false,
+            delimiter: None,
+        }
+    }
+
+    /// Whether CSV and TSV output starts with a header row. Markdown tables
+    /// always have one.
+    ///
+    /// CSV, TSV and Markdown output get a `target` column if any row knows
+    /// its bench target, and a trailing `file` column if any row is tagged
+    /// with a file.
+    pub fn header(mut self, header: bool) -> Self {
+        self.header = header;
+        self
+    }
+
+    /// Overrides the delimiter of CSV and TSV output.
+    pub fn delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = Some(delimiter);
+        self
+    }
+
+    pub fn write_rows(&mut self, rows: &[Row]) -> io::Result<()> {
+        match self.format {
+            Format::Csv => self.write_csv(rows, ','),
+            Format::Tsv => self.write_csv(rows, '\t'),
+            Format::Json => {
+                let rows: Vec<_> = rows.iter().map(Row::to_json).collect();
+                serde_json::to_writer_pretty(&mut self.writer, &rows)?;
+                writeln!(self.writer)
+            }
+            Format::Ndjson => {
+                for row in rows {
+                    serde_json::to_writer(&mut self.writer, &row.to_json())?;
+                    writeln!(self.writer)?;
+                }
+                Ok(())
+            }
+            Format::Markdown => self.write_markdown(rows),
+        }?;
+        self.writer.flush()
+    }
+
+    /// Writes one row per aggregate of repeated runs, with the columns
+    /// `name`, `runs`, `min`, `median`, `mean` and `stddev`, and a trailing
+    /// `target` column if any aggregate knows its bench target.
+    pub fn write_aggregates(&mut self, aggregates: &[Aggregate]) -> io::Result<()> {
+        let with_target = aggregates.iter().any(|agg| agg.target.is_some());
+        let to_json = |agg: &Aggregate| {
+            serde_json::json!({
+                "name": agg.name,
+                "target": agg.target,
+                "runs": agg.runs,
+                "min": agg.min,
+                "median": agg.median,
+                "mean": agg.mean,
+                "stddev": agg.stddev,
+            })
+        };
+        match self.format {
+            Format::Csv | Format::Tsv => {
+                let default = if self.format == Format::Csv {
+                    ','
+                } else {
+                    '\t'
+                };
+                let delimiter = self.delimiter.unwrap_or(default);
+                let mut csv = CsvWriter::new(&mut self.writer).delimiter(delimiter);
+                if self.header {
+                    let header = ["name", "runs", "min", "median", "mean", "stddev", "target"];
+                    csv.write_record(&header[..if with_target { 7 } else { 6 }])?;
+                }
+                for agg in aggregates {
+                    let mut record = vec![
+                        agg.name.clone(),
+                        agg.runs.to_string(),
+                        agg.min.to_string(),
+                        agg.median.to_string(),
+                        agg.mean.to_string(),
+                        agg.stddev.to_string(),
+                    ];
+                    if with_target {
+                        record.push(agg.target.clone().unwrap_or_default());
+                    }
+                    csv.write_record(record)?;
+                }
+                Ok(())
+            }
+            Format::Json => {
+                let rows: Vec<_> = aggregates.iter().map(to_json).collect();
+                serde_json::to_writer_pretty(&mut self.writer, &rows)?;
+                writeln!(self.writer)
+            }
+            Format::Ndjson => {
+                for agg in aggregates {
+                    serde_json::to_writer(&mut self.writer, &to_json(agg))?;
+                    writeln!(self.writer)?;
+                }
+                Ok(())
+            }
+            Format::Markdown => {
+                let (target_header, target_align) = if with_target {
+                    (" Target |", "--------|")
+                } else {
+                    ("", "")
+                };
+                writeln!(
+                    self.writer,
+                    "| Name | Runs | Min | Median | Mean | Stddev |{}",
+                    target_header
+                )?;
+                writeln!(
+                    self.writer,
+                    "|------|-----:|----:|-------:|-----:|-------:|{}",
+                    target_align
+                )?;
+                for agg in aggregates {
+                    let human = |ns: f64| HumanDuration::from_ns(ns).to_string();
+                    let stddev = match relative_percent(agg.stddev, agg.mean, 2) {
+                        Some(percent) => format!("{} ({})", human(agg.stddev), percent),
+                        None => human(agg.stddev),
+                    };
+                    write!(
+                        self.writer,
+                        "| {} | {} | {} | {} | {} | {} |",
+                        escape_markdown(&agg.name),
+                        agg.runs,
+                        human(agg.min),
+                        human(agg.median),
+                        human(agg.mean),
+                        stddev
+                    )?;
+                    if with_target {
+                        let target = agg.target.as_deref().unwrap_or_default();
+                        write!(self.writer, " {} |", escape_markdown(target))?;
+                    }
+                    writeln!(self.writer)?;
+                }
+                Ok(())
+            }
+        }?;
+        self.writer.flush()
+    }
+
+    fn write_csv(&mut self, rows: &[Row], delimiter: char) -> io::Result<()> {
+        let delimiter = self.delimiter.unwrap_or(delimiter);
+        let (with_target, with_file) = (has_targets(rows), has_files(rows));
+        let mut csv = CsvWriter::new(&mut self.writer).delimiter(delimiter);
+        if self.header {
+            let mut header = vec!["name", "value", "variance", "throughput", "outcome", "unit"];
+            if with_target {
+                header.push("target");
+            }
+            if with_file {
+                header.push("file");
+            }
+            csv.write_record(header)?;
+        }
+        for row in rows {
+            let mut record = vec![
+                row.name.clone(),
+                display_or_empty(row.value),
+                display_or_empty(row.variance),
+                display_or_empty(row.throughput),
+                row.outcome.to_string(),
+                row.unit.clone(),
+            ];
+            if with_target {
+                record.push(row.target.clone().unwrap_or_default());
+            }
+            if with_file {
+                record.push(row.file.clone().unwrap_or_default());
+            }
+            csv.write_record(record)?;
+        }
+        Ok(())
+    }
+
+    fn write_markdown(&mut self, rows: &[Row]) -> io::Result<()> {
+        let (with_target, with_file) = (has_targets(rows), has_files(rows));
+        let (mut extra_header, mut extra_align) = (String::new(), String::new());
+        if with_target {
+            extra_header.push_str(" Target |");
+            extra_align.push_str("--------|");
+        }
+        if with_file {
+            extra_header.push_str(" File |");
+            extra_align.push_str("------|");
+        }
+        writeln!(
+            self.writer,
+            "| Name | Value | Variance | Throughput | Outcome |{}",
+            extra_header
+        )?;
+        writeln!(
+            self.writer,
+            "|------|------:|---------:|-----------:|---------|{}",
+            extra_align
+        )?;
+        for row in rows {
+            let (value, variance) = match row.unit.as_str() {
+                "ns" => (
+                    row.value.map(|ns| HumanDuration::from_ns(ns).to_string()),
+                    row.variance.map(|variance| {
+                        let spread = HumanDuration::from_ns(variance);
+                        match row.value.and_then(|ns| relative_percent(variance, ns, 2)) {
+                            Some(percent) => format!("± {} ({})", spread, percent),
+                            None => format!("± {}", spread),
+                        }
+                    }),
+                ),
+                unit => (
+                    row.value.map(|value| {
+                        let value = group_thousands(&value.to_string());
+                        format!("{} {}", value, unit).trim_end().to_string()
+                    }),
+                    None,
+                ),
+            };
+            write!(
+                self.writer,
+                "| {} | {} | {} | {} | {} |",
+                escape_markdown(&row.name),
+                value.unwrap_or_default(),
+                variance.unwrap_or_default(),
+                row.throughput
+                    .map(|throughput| format!("{} MB/s", throughput))
+                    .unwrap_or_default(),
+                row.outcome
+            )?;
+            if with_target {
+                let target = row.target.as_deref().unwrap_or_default();
+                write!(self.writer, " {} |", escape_markdown(target))?;
+            }
+            if with_file {
+                let file = row.file.as_deref().unwrap_or_default();
+                write!(self.writer, " {} |", escape_markdown(file))?;
+            }
+            writeln!(self.writer)?;
+        }
+        Ok(())
+    }
+}
+
+fn has_targets(rows: &[Row]) -> bool {
+    rows.iter().any(|row| row.target.is_some())
+}
+
+fn has_files(rows: &[Row]) -> bool {
+    rows.iter().any(|row| row.file.is_some())
+}
+
+fn escape_markdown(text: &str) -> String {
+    text.replace('|', "\\|")
+}
+
+fn display_or_empty<T: fmt::Display>(value: Option<T>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::BufReader;
+
+    use super::*;
+    use crate::parse_lines;
+
+    const DATA: &str = "
+test a::fast ... bench:         950 ns/iter (+/- 12)
+test a::slow ... bench:  95,653,541 ns/iter (+/- 1,234) = 10 MB/s
+test a::skip ... ignored
+";
+
+    fn write(format: Format) -> String {
+        let benchmarks = parse_lines(BufReader::new(DATA.as_bytes())).unwrap();
+        let rows: Vec<Row> = benchmarks.iter().flat_map(Row::from_benchmark).collect();
+        let mut writer = RowWriter::new(Vec::new(), format).header(true);
+        writer.write_rows(&rows).unwrap();
+        String::from_utf8(writer.writer).unwrap()
+    }
+
+    #[test]
+    fn format_test() {
+        assert_eq!(
+            write(Format::Tsv),
+            "name\tvalue\tvariance\tthroughput\toutcome\tunit\n\
+             a::fast\t950\t12\t\tok\tns\n\
+             a::slow\t95653541\t1234\t10\tok\tns\n\
+             a::skip\t\t\t\tignored\t\n"
+        );
+
+        let ndjson = write(Format::Ndjson);
+        let lines: Vec<serde_json::Value> = ndjson
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[1]["value"], 95653541.0);
+        assert_eq!(lines[1]["throughput"], 10);
+        assert_eq!(lines[2]["value"], serde_json::Value::Null);
+        assert_eq!(lines[2]["outcome"], "ignored");
+
+        let json: serde_json::Value = serde_json::from_str(&write(Format::Json)).unwrap();
+        assert_eq!(json.as_array().unwrap().len(), 3);
+
+        let markdown = write(Format::Markdown);
+        assert!(markdown.contains("| a::fast | 950.00 ns | ± 12.00 ns (±1.26%) |  | ok |"));
+        assert!(markdown.contains("| a::slow | 95.65 ms | ± 1.23 µs (±0.00%) | 10 MB/s | ok |"));
+
+        assert_eq!("md".parse(), Ok(Format::Markdown));
+        assert!("xml".parse::<Format>().is_err());
+    }
+
+    #[test]
+    fn counter_rows_test() {
+        let data =
+            "bench_fibonacci\n  Instructions:                1735|1735            (No change)\n";
+        let benchmarks = parse_lines(BufReader::new(data.as_bytes())).unwrap();
+        let rows = Row::from_benchmark(&benchmarks[0]);
+        let mut writer = RowWriter::new(Vec::new(), Format::Csv).header(true);
+        writer.write_rows(&rows).unwrap();
+        assert_eq!(
+            String::from_utf8(writer.writer).unwrap(),
+            "name,value,variance,throughput,outcome,unit\n\
+             bench_fibonacci/Instructions,1735,,,ok,\n"
+        );
+    }
+
+    #[test]
+    fn file_column_test() {
+        let benchmarks = parse_lines(BufReader::new(DATA.as_bytes())).unwrap();
+        let rows: Vec<Row> = Row::from_benchmark(&benchmarks[0])
+            .into_iter()
+            .map(|row| row.with_file("runs/a,b.txt"))
+            .collect();
+        let mut writer = RowWriter::new(Vec::new(), Format::Csv).header(true);
+        writer.write_rows(&rows).unwrap();
+        assert_eq!(
+            String::from_utf8(writer.writer).unwrap(),
+            "name,value,variance,throughput,outcome,unit,file\n\
+             a::fast,950,12,,ok,ns,\"runs/a,b.txt\"\n"
+        );
+
+        let mut writer = RowWriter::new(Vec::new(), Format::Markdown);
+        writer.write_rows(&rows).unwrap();
+        let markdown = String::from_utf8(writer.writer).unwrap();
+        assert!(markdown.starts_with("| Name | Value | Variance | Throughput | Outcome | File |"));
+        assert!(markdown.ends_with("| ok | runs/a,b.txt |\n"));
+    }
+
+    #[test]
+    fn target_column_test() {
+        let data = "
+     Running benches/a.rs (target/release/deps/a-3f5b1cbd0a8d1a2e)
+test bench_x ... bench:         100 ns/iter (+/- 1)
+     Running benches/b.rs (target/release/deps/b-3f5b1cbd0a8d1a2e)
+test bench_x ... bench:       5,000 ns/iter (+/- 1)
+";
+        let benchmarks = parse_lines(BufReader::new(data.as_bytes())).unwrap();
+        let rows: Vec<Row> = benchmarks.iter().flat_map(Row::from_benchmark).collect();
+        let mut writer = RowWriter::new(Vec::new(), Format::Csv).header(true);
+        writer.write_rows(&rows).unwrap();
+        assert_eq!(
+            String::from_utf8(writer.writer).unwrap(),
+            "name,value,variance,throughput,outcome,unit,target\n\
+             bench_x,100,1,,ok,ns,a\n\
+             bench_x,5000,1,,ok,ns,b\n"
+        );
+
+        let rows: Vec<Row> = rows
+            .into_iter()
+            .map(|row| row.with_file("run.txt"))
+            .collect();
+        let mut writer = RowWriter::new(Vec::new(), Format::Markdown);
+        writer.write_rows(&rows).unwrap();
+        let markdown = String::from_utf8(writer.writer).unwrap();
+        assert!(markdown.starts_with(
+            "| Name | Value | Variance | Throughput | Outcome | Target | File |\n\
+             |------|------:|---------:|-----------:|---------|--------|------|\n"
+        ));
+        assert!(markdown.ends_with("| ok | b | run.txt |\n"));
+
+        assert_eq!(rows[0].to_json()["target"], "a");
+    }
+
+    #[test]
+    fn aggregates_test() {
+        let runs: Vec<Vec<Benchmark>> = ["1,000", "1,200", "1,100"]
+            .iter()
+            .map(|ns| {
+                let line = format!("test a::b ... bench: {} ns/iter (+/- 5)", ns);
+                parse_lines(BufReader::new(line.as_bytes())).unwrap()
+            })
+            .collect();
+        let aggregates = crate::aggregate(&runs);
+
+        let mut writer = RowWriter::new(Vec::new(), Format::Csv).header(true);
+        writer.write_aggregates(&aggregates).unwrap();
+        assert_eq!(
+            String::from_utf8(writer.writer).unwrap(),
+            "name,runs,min,median,mean,stddev\na::b,3,1000,1100,1100,100\n"
+        );
+
+        let mut writer = RowWriter::new(Vec::new(), Format::Markdown);
+        writer.write_aggregates(&aggregates).unwrap();
+        let markdown = String::from_utf8(writer.writer).unwrap();
+        assert!(
+            markdown.ends_with("| a::b | 3 | 1.00 µs | 1.10 µs | 1.10 µs | 100.00 ns (±9.09%) |\n")
+        );
+
+        let mut aggregates = aggregates;
+        aggregates[0].target = Some("codecs".to_string());
+        let mut writer = RowWriter::new(Vec::new(), Format::Csv).header(true);
+        writer.write_aggregates(&aggregates).unwrap();
+        assert_eq!(
+            String::from_utf8(writer.writer).unwrap(),
+            "name,runs,min,median,mean,stddev,target\na::b,3,1000,1100,1100,100,codecs\n"
+        );
+    }
+}
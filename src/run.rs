@@ -0,0 +1,234 @@
+//! Grouping of benchmarks by the bench binary that ran them.
+
+use std::{
+    fmt,
+    io::{self, BufRead},
+};
+
+use crate::{BenchOutcome, BenchSource, Benchmark, Event, Parser};
+
+/// libtest's `test result:` line.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TestSummary {
+    /// `ok` or `FAILED`
+    pub ok: bool,
+    pub passed: usize,
+    pub failed: usize,
+    pub ignored: usize,
+    /// Number of benchmarks
+    pub measured: usize,
+    pub filtered_out: usize,
+}
+
+/// The output of a single bench binary.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BenchRun {
+    /// The binary, if cargo's `Running` line was parsed
+    pub source: Option<BenchSource>,
+    /// Number of tests and benchmarks from libtest's `running N tests`
+    pub expected: Option<usize>,
+    pub summary: Option<TestSummary>,
+    pub benchmarks: Vec<Benchmark>,
+}
+
+impl BenchRun {
+    /// Signs that benchmarks of this run are missing.
+    ///
+    /// Only libtest prints the counts to check against, so runs of other
+    /// harnesses never have warnings.
+    pub fn warnings(&self) -> Vec<RunWarning> {
+        let measured = self
+            .benchmarks
+            .iter()
+            .filter(|bench| bench.outcome == BenchOutcome::Ok)
+            .count();
+        match (self.expected, &self.summary) {
+            (Some(expected), None) => vec![RunWarning::Truncated {
+                expected,
+                parsed: self.benchmarks.len(),
+            }],
+            (_, Some(summary)) if summary.measured != measured => {
+                vec![RunWarning::MeasuredMismatch {
+                    measured: summary.measured,
+                    parsed: measured,
+                }]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.source.is_none()
+            && self.expected.is_none()
+            && self.summary.is_none()
+            && self.benchmarks.is_empty()
+    }
+}
+
+/// A sign of benchmarks missing from a [`BenchRun`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RunWarning {
+    /// libtest announced `expected` tests but printed no summary, e.g. because
+    /// the run was killed.
+    Truncated { expected: usize, parsed: usize },
+    /// libtest reports a different number of benchmarks than was parsed.
+    MeasuredMismatch { measured: usize, parsed: usize },
+}
+
+impl fmt::Display for RunWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RunWarning::Truncated { expected, parsed } => write!(
+                f,
+                "output truncated, {} of {} tests found and no test result",
+                parsed, expected
+            ),
+            RunWarning::MeasuredMismatch { measured, parsed } => {
+                write!(f, "{} benchmarks measured, but {} parsed", measured, parsed)
+            }
+        }
+    }
+}
+
+/// Collects events into runs.
+#[derive(Default)]
+struct Runs {
+    runs: Vec<BenchRun>,
+    current: BenchRun,
+}
+
+impl Runs {
+    fn push(&mut self, event: Event) {
+        match event {
+            Event::Running(source) => {
+                self.end_run();
+                self.current.source = Some(source);
+            }
+            Event::Started { test_count } => {
+                // Concatenated libtest output without cargo's `Running` lines
+                if self.current.expected.is_some() || self.current.summary.is_some() {
+                    let source = self.current.source.clone();
+                    self.end_run();
+                    self.current.source = source;
+                }
+                self.current.expected = Some(test_count);
+            }
+            Event::Benchmark(bench) => self.current.benchmarks.push(bench),
+            Event::Summary(summary) => self.current.summary = Some(summary),
+        }
+    }
+
+    fn end_run(&mut self) {
+        let run = std::mem::take(&mut self.current);
+        if !run.is_empty() {
+            self.runs.push(run);
+        }
+    }
+
+    fn finish(mut self) -> Vec<BenchRun> {
+        self.end_run();
+        self.runs
+    }
+}
+
+/// Parse benchmarks from a buffered reader, grouped by bench binary.
+pub fn parse_runs<B: BufRead>(buffer: B) -> io::Result<Vec<BenchRun>> {
+    let mut runs = Runs::default();
+    let mut parser = Parser::new();
+    for result in buffer.lines() {
+        let _ = parser.push_line(&result?);
+        parser.drain_events().for_each(|event| runs.push(event));
+    }
+    parser.finish();
+    parser.drain_events().for_each(|event| runs.push(event));
+    Ok(runs.finish())
+}
+
+/// Parse the benchmarks of all runs from a buffered reader, together with the
+/// warnings of the runs, prefixed with their bench target if known.
+pub fn parse_benchmarks<B: BufRead>(buffer: B) -> io::Result<(Vec<Benchmark>, Vec<String>)> {
+    let runs = parse_runs(buffer)?;
+    let warnings = runs
+        .iter()
+        .flat_map(|run| {
+            run.warnings()
+                .into_iter()
+                .map(move |warning| match &run.source {
+                    Some(source) => format!("{}: {}", source.target, warning),
+                    None => warning.to_string(),
+                })
+        })
+        .collect();
+    let benchmarks = runs.into_iter().flat_map(|run| run.benchmarks).collect();
+    Ok((benchmarks, warnings))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::BufReader;
+
+    use super::*;
+
+    const RUNS_DATA: &str = r#"     Running unittests src/lib.rs (target/release/deps/tantivy-3f5b1cbd0a8d1a2e)
+
+running 2 tests
+test a::first  ... bench:         100 ns/iter (+/- 3)
+test a::second ... bench:         200 ns/iter (+/- 3)
+
+test result: ok. 0 passed; 0 failed; 0 ignored; 2 measured; 0 filtered out; finished in 0.10s
+
+     Running benches/codecs.rs (target/release/deps/codecs-3f5b1cbd0a8d1a2e)
+
+running 3 tests
+test b::first  ... bench:         100 ns/iter (+/- 3)
+test b::second ... bench:         200 ns/iter (+/- 3)
+"#;
+
+    #[test]
+    fn parse_runs_test() {
+        let runs = parse_runs(BufReader::new(RUNS_DATA.as_bytes())).unwrap();
+        assert_eq!(runs.len(), 2);
+
+        assert_eq!(runs[0].source.as_ref().unwrap().target, "tantivy");
+        assert_eq!(runs[0].expected, Some(2));
+        assert_eq!(runs[0].summary.as_ref().unwrap().measured, 2);
+        assert_eq!(runs[0].warnings(), &[]);
+
+        assert_eq!(runs[1].source.as_ref().unwrap().target, "codecs");
+        assert_eq!(runs[1].benchmarks.len(), 2);
+        assert_eq!(
+            runs[1].warnings(),
+            &[RunWarning::Truncated {
+                expected: 3,
+                parsed: 2
+            }]
+        );
+    }
+
+    #[test]
+    fn parse_benchmarks_test() {
+        let (benchmarks, warnings) =
+            parse_benchmarks(BufReader::new(RUNS_DATA.as_bytes())).unwrap();
+        assert_eq!(benchmarks.len(), 4);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with("codecs: "));
+    }
+
+    #[test]
+    fn measured_mismatch_test() {
+        let data = "running 2 tests\n\
+                    test a::first ... bench: 100 ns/iter (+/- 3)\n\
+                    test a::second ... bench: 1.0e2 ns/iter (+/- 3)\n\
+                    test result: ok. 0 passed; 0 failed; 0 ignored; 2 measured; 0 filtered out\n";
+        let runs = parse_runs(BufReader::new(data.as_bytes())).unwrap();
+        assert_eq!(
+            runs[0].warnings(),
+            &[RunWarning::MeasuredMismatch {
+                measured: 2,
+                parsed: 1
+            }]
+        );
+    }
+}
@@ -0,0 +1,197 @@
+//! Selecting and ordering benchmarks, e.g. for `cargobench_to_csv --filter`.
+
+use std::{cmp::Ordering, str::FromStr};
+
+use regex::Regex;
+
+use crate::{BenchOutcome, Benchmark};
+
+/// Keeps benchmarks whose name or shortname matches `include`, unless it
+/// matches `exclude`.
+#[derive(Clone, Debug, Default)]
+pub struct Filter {
+    pub include: Option<Regex>,
+    pub exclude: Option<Regex>,
+}
+
+impl Filter {
+    pub fn matches(&self, benchmark: &Benchmark) -> bool {
+        let is_match =
+            |regex: &Regex| regex.is_match(&benchmark.name) || regex.is_match(&benchmark.shortname);
+        self.include.as_ref().map_or(true, is_match) && !self.exclude.as_ref().is_some_and(is_match)
+    }
+}
+
+/// The value benchmarks are sorted by.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SortKey {
+    Name,
+    Ns,
+    Variance,
+    Throughput,
+}
+
+/// A [`SortKey`] and direction, parsed from `key`, `key:asc` or `key:desc`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sort {
+    pub key: SortKey,
+    pub descending: bool,
+}
+
+impl Sort {
+    /// Compares two benchmarks. Benchmarks without the key's value sort last
+    /// in both directions: without a throughput, or for `ns` and `variance`
+    /// without a time because they did not run or only report counters.
+    pub fn compare(&self, a: &Benchmark, b: &Benchmark) -> Ordering {
+        match self.key {
+            SortKey::Name => self.compare_values(Some(&a.name), Some(&b.name), Ord::cmp),
+            SortKey::Ns => self.compare_values(timed(a), timed(b), |a, b| a.ns.total_cmp(&b.ns)),
+            SortKey::Variance => {
+                self.compare_values(timed(a), timed(b), |a, b| a.variance.total_cmp(&b.variance))
+            }
+            SortKey::Throughput => self.compare_values(a.throughput, b.throughput, Ord::cmp),
+        }
+    }
+
+    /// Orders present values by `cmp` in the sort direction, missing ones last.
+    fn compare_values<T>(
+        &self,
+        a: Option<T>,
+        b: Option<T>,
+        cmp: impl FnOnce(&T, &T) -> Ordering,
+    ) -> Ordering {
+        match (a, b) {
+            (Some(a), Some(b)) if self.descending => cmp(&a, &b).reverse(),
+            (Some(a), Some(b)) => cmp(&a, &b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        }
+    }
+
+    /// Sorts benchmarks in place, keeping the input order of equal ones.
+    pub fn sort(&self, benchmarks: &mut [Benchmark]) {
+        benchmarks.sort_by(|a, b| self.compare(a, b));
+    }
+}
+
+/// The benchmark if it ran and measured a time.
+fn timed(bench: &Benchmark) -> Option<&Benchmark> {
+    let has_time = bench.outcome == BenchOutcome::Ok && bench.measurement("time").is_some();
+    has_time.then_some(bench)
+}
+
+impl FromStr for Sort {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (key, direction) = s.split_once(':').unwrap_or((s, "asc"));
+        let key = match key {
+            "name" => SortKey::Name,
+            "ns" => SortKey::Ns,
+            "variance" => SortKey::Variance,
+            "throughput" => SortKey::Throughput,
+            _ => {
+                return Err(format!(
+                    "unknown sort key '{}', expected name, ns, variance or throughput",
+                    key
+                ))
+            }
+        };
+        let descending = match direction {
+            "asc" => false,
+            "desc" => true,
+            _ => {
+                return Err(format!(
+                    "unknown sort direction '{}', expected asc or desc",
+                    direction
+                ))
+            }
+        };
+        Ok(Sort { key, descending })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::BufReader;
+
+    use super::*;
+    use crate::parse_lines;
+
+    const DATA: &str = "
+test codecs::bench_fast ... bench:         950 ns/iter (+/- 12)
+test codecs::bench_slow ... bench:      95,653 ns/iter (+/- 1,234) = 10 MB/s
+test store::bench_fast  ... bench:         100 ns/iter (+/- 50) = 20 MB/s
+";
+
+    fn names(benchmarks: &[Benchmark]) -> Vec<&str> {
+        benchmarks.iter().map(|bench| bench.name.as_str()).collect()
+    }
+
+    #[test]
+    fn filter_test() {
+        let benchmarks = parse_lines(BufReader::new(DATA.as_bytes())).unwrap();
+        let filter = Filter {
+            include: Some(Regex::new("^bench_fast$").unwrap()),
+            exclude: Some(Regex::new("^store::").unwrap()),
+        };
+        let kept: Vec<Benchmark> = benchmarks
+            .into_iter()
+            .filter(|bench| filter.matches(bench))
+            .collect();
+        assert_eq!(names(&kept), ["codecs::bench_fast"]);
+    }
+
+    #[test]
+    fn sort_test() {
+        let mut benchmarks = parse_lines(BufReader::new(DATA.as_bytes())).unwrap();
+        "ns".parse::<Sort>().unwrap().sort(&mut benchmarks);
+        assert_eq!(
+            names(&benchmarks),
+            [
+                "store::bench_fast",
+                "codecs::bench_fast",
+                "codecs::bench_slow"
+            ]
+        );
+        "throughput:desc"
+            .parse::<Sort>()
+            .unwrap()
+            .sort(&mut benchmarks);
+        assert_eq!(
+            names(&benchmarks),
+            [
+                "store::bench_fast",
+                "codecs::bench_slow",
+                "codecs::bench_fast"
+            ]
+        );
+        "name".parse::<Sort>().unwrap().sort(&mut benchmarks);
+        assert_eq!(benchmarks[2].name, "store::bench_fast");
+
+        // Benchmarks without a time or throughput sort last in both directions
+        let data = "
+test a::ignored ... ignored
+test a::slow    ... bench:         200 ns/iter (+/- 1)
+test a::fast    ... bench:         100 ns/iter (+/- 1) = 10 MB/s
+bench_fibonacci
+  Instructions:                1735|1735            (No change)
+";
+        let mut benchmarks = parse_lines(BufReader::new(data.as_bytes())).unwrap();
+        for sort in ["ns", "variance", "throughput"] {
+            for direction in ["asc", "desc"] {
+                let sort: Sort = format!("{}:{}", sort, direction).parse().unwrap();
+                sort.sort(&mut benchmarks);
+                let last = names(&benchmarks[2..]);
+                assert!(last.contains(&"a::ignored"), "{:?} {:?}", sort, last);
+                assert!(last.contains(&"bench_fibonacci"), "{:?} {:?}", sort, last);
+            }
+        }
+        "ns:desc".parse::<Sort>().unwrap().sort(&mut benchmarks);
+        assert_eq!(names(&benchmarks[..2]), ["a::slow", "a::fast"]);
+
+        assert!("ns:up".parse::<Sort>().is_err());
+        assert!("speed".parse::<Sort>().is_err());
+    }
+}
@@ -0,0 +1,69 @@
+//! Statistical tests on benchmark samples.
+
+/// Two-sided p-value of the Mann-Whitney U test, whether the samples come
+/// from the same distribution, using the normal approximation with tie and
+/// continuity correction. Needs about 8 samples on each side to be accurate.
+pub(crate) fn mann_whitney_u(a: &[f64], b: &[f64]) -> f64 {
+    let (n1, n2) = (a.len() as f64, b.len() as f64);
+    let n = n1 + n2;
+    let mut values: Vec<(f64, bool)> = a
+        .iter()
+        .map(|&value| (value, true))
+        .chain(b.iter().map(|&value| (value, false)))
+        .collect();
+    values.sort_by(|x, y| x.0.total_cmp(&y.0));
+
+    // Sum of the ranks of `a`, tied values get the average of their ranks
+    let mut rank_sum = 0.0;
+    let mut tie_correction = 0.0;
+    let mut start = 0;
+    while start < values.len() {
+        let end = start
+            + values[start..]
+                .iter()
+                .take_while(|(value, _)| *value == values[start].0)
+                .count();
+        let ties = (end - start) as f64;
+        let rank = (start + end + 1) as f64 / 2.0;
+        rank_sum += rank * values[start..end].iter().filter(|(_, in_a)| *in_a).count() as f64;
+        tie_correction += ties * ties * ties - ties;
+        start = end;
+    }
+
+    let u = rank_sum - n1 * (n1 + 1.0) / 2.0;
+    let mean = n1 * n2 / 2.0;
+    let variance = n1 * n2 / 12.0 * ((n + 1.0) - tie_correction / (n * (n - 1.0)));
+    if variance <= 0.0 || !variance.is_finite() {
+        return 1.0;
+    }
+    let z = ((u - mean).abs() - 0.5).max(0.0) / variance.sqrt();
+    erfc(z / std::f64::consts::SQRT_2)
+}
+
+/// Complementary error function for `x >= 0`, Abramowitz and Stegun 7.1.26,
+/// with an absolute error below 1.5e-7.
+fn erfc(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.3275911 * x);
+    let poly = t
+        * (0.254829592
+            + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    poly * (-x * x).exp()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mann_whitney_u_test() {
+        let a: Vec<f64> = (0..20).map(|i| 100.0 + (i % 5) as f64).collect();
+        let same: Vec<f64> = (0..20).map(|i| 100.0 + ((i + 2) % 5) as f64).collect();
+        let slower: Vec<f64> = (0..20).map(|i| 103.0 + (i % 5) as f64).collect();
+        assert!(mann_whitney_u(&a, &same) > 0.5);
+        assert!(mann_whitney_u(&a, &slower) < 0.01);
+        assert_eq!(mann_whitney_u(&[1.0; 10], &[1.0; 10]), 1.0);
+
+        assert!((erfc(0.0) - 1.0).abs() < 1e-6);
+        assert!((erfc(1.0) - 0.157299).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,51 @@
+//! Conversion between the units used by the different benchmark harnesses.
+//!
+//! Durations are converted to ns, byte throughput to MB/s and element
+//! throughput to elem/s.
+
+/// Factor to convert a time in `unit` to ns.
+pub(crate) fn time_unit(unit: &str) -> Option<(f64, &'static str)> {
+    let factor = match unit {
+        "ps" => 1e-3,
+        "ns" => 1.0,
+        "µs" | "us" => 1e3,
+        "ms" => 1e6,
+        "s" => 1e9,
+        _ => return None,
+    };
+    Some((factor, "ns"))
+}
+
+/// Factor to convert a throughput in `unit` to MB/s or elem/s.
+pub(crate) fn throughput_unit(unit: &str) -> Option<(f64, &'static str)> {
+    let unit = unit.strip_suffix("/s")?;
+    let (prefix, base, factor, converted) = if let Some(prefix) = unit
+        .strip_suffix("elem")
+        .or_else(|| unit.strip_suffix("item"))
+    {
+        (prefix, 1000.0, 1.0, "elem/s")
+    } else if let Some(prefix) = unit.strip_suffix("iB") {
+        (prefix, 1024.0, 1e-6, "MB/s")
+    } else {
+        (unit.strip_suffix('B')?, 1000.0, 1e-6, "MB/s")
+    };
+    let exponent = match prefix {
+        "" => 0,
+        "K" | "k" => 1,
+        "M" => 2,
+        "G" => 3,
+        "T" => 4,
+        _ => return None,
+    };
+    Some((f64::powi(base, exponent) * factor, converted))
+}
+
+/// Parses a `value unit` pair like `1.5 µs`, converted with `to_unit`.
+pub(crate) fn parse_quantity(
+    text: &str,
+    to_unit: fn(&str) -> Option<(f64, &'static str)>,
+) -> Option<(f64, &'static str)> {
+    let (value, unit) = text.trim().split_once(' ')?;
+    let (factor, unit) = to_unit(unit.trim_start())?;
+    Some((value.parse::<f64>().ok()? * factor, unit))
+}
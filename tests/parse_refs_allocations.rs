@@ -0,0 +1,58 @@
+//! Checks that `parse_refs` does not allocate per line. This lives in its own
+//! test binary, as the counting allocator replaces the global allocator.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+};
+
+use rust_bench_parser::parse_refs;
+
+/// Counts the allocations of the current thread, so that tests running in
+/// parallel do not interfere.
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+const TEST_DATA: &str = r#"running 3 tests
+test fastfield::multivalued::bench::bench_multi_value_ff_creation                                                        ... bench:  95,653,541 ns/iter (+/- 1,410,738)
+test fastfield::multivalued::bench::bench_multi_value_ff_creation_with_sorting                                           ... bench: 103,466,980 ns/iter (+/- 6,247,651)
+test fastfield::multivalued::bench::bench_multi_value_fflookup                                                           ... bench:   1,330,510 ns/iter (+/- 217,966)"#;
+
+#[test]
+fn parse_refs_allocations_test() {
+    let allocations = |lines: usize| {
+        let text = TEST_DATA
+            .lines()
+            .cycle()
+            .take(lines)
+            .collect::<Vec<_>>()
+            .join("\n");
+        let before = ALLOCATIONS.with(|count| count.get());
+        let parsed = parse_refs(&text).count();
+        let allocations = ALLOCATIONS.with(|count| count.get()) - before;
+        (parsed, allocations)
+    };
+    // Warms up the regex and its cache for this thread
+    allocations(10);
+    let (parsed_few, few) = allocations(10);
+    let (parsed_many, many) = allocations(10_000);
+    assert!(parsed_many > parsed_few);
+    assert_eq!(few, many);
+}